@@ -2,7 +2,7 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::{fs, path};
 
@@ -16,16 +16,87 @@ const VAL_EXTERNAL: &str = "external";
 
 pub struct Parser {}
 
+/// A single parsed attribute pattern together with the attributes it assigns.
+///
+/// Patterns are matched using gitattributes-style glob semantics: `*` matches
+/// any run of characters but never crosses a `/`, `**` matches zero or more
+/// path components, `?` matches a single character, and a trailing `/`
+/// restricts the pattern to a directory and everything beneath it.
 #[derive(Clone, Debug, Eq, PartialEq, Default)]
 pub struct Item {
     pub pattern: PathBuf,
     pub attributes: HashMap<String, String>,
+    pub crcs: Vec<u32>,
+    /// Whether the source pattern was restricted to directories (i.e. had a
+    /// trailing `/` before `gix_attributes` stripped it off).
+    dir_only: bool,
+}
+
+impl Item {
+    fn is_dir_pattern(&self) -> bool {
+        self.dir_only
+    }
+
+    /// Check whether `path` matches this item's pattern.
+    fn matches(&self, path_segs: &[&str]) -> bool {
+        let pattern = self.pattern.to_string_lossy();
+        let pattern = pattern.trim_end_matches('/');
+        let mut pat_segs = split_segments(pattern);
+        if self.is_dir_pattern() {
+            pat_segs.push("**");
+        }
+        match_segments(&pat_segs, path_segs)
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Default)]
 pub struct Attributes {
-    pub items: HashMap<PathBuf, HashMap<String, String>>,
-    pub crcs: HashMap<PathBuf, Vec<u32>>,
+    /// Parsed patterns in file order. Lookups walk this list from the back
+    /// so that the last matching pattern takes precedence, mirroring
+    /// gitattributes semantics.
+    pub items: Vec<Item>,
+}
+
+/// Split a `/`-separated path into its non-empty components.
+fn split_segments(s: &str) -> Vec<&str> {
+    s.split('/').filter(|s| !s.is_empty()).collect()
+}
+
+/// Whether a single path component contains glob metacharacters.
+fn is_literal_segment(seg: &str) -> bool {
+    !seg.contains(['*', '?', '[', ']'])
+}
+
+/// Match a single path component against a single pattern component,
+/// supporting `*` (any run of characters) and `?` (a single character).
+fn match_segment(pat: &str, s: &str) -> bool {
+    fn helper(pat: &[u8], s: &[u8]) -> bool {
+        match (pat.first(), s.first()) {
+            (None, None) => true,
+            (Some(b'*'), _) => helper(&pat[1..], s) || (!s.is_empty() && helper(pat, &s[1..])),
+            (Some(b'?'), Some(_)) => helper(&pat[1..], &s[1..]),
+            (Some(a), Some(b)) if a == b => helper(&pat[1..], &s[1..]),
+            _ => false,
+        }
+    }
+    helper(pat.as_bytes(), s.as_bytes())
+}
+
+/// Match a full sequence of pattern components against a sequence of path
+/// components, with `**` crossing zero or more directories.
+fn match_segments(pat: &[&str], path: &[&str]) -> bool {
+    match pat.first() {
+        None => path.is_empty(),
+        Some(&"**") => {
+            if pat.len() == 1 {
+                return true;
+            }
+            (0..=path.len()).any(|i| match_segments(&pat[1..], &path[i..]))
+        }
+        Some(seg) => {
+            !path.is_empty() && match_segment(seg, path[0]) && match_segments(&pat[1..], &path[1..])
+        }
+    }
 }
 
 impl Attributes {
@@ -34,8 +105,8 @@ impl Attributes {
         let content = fs::read(path)?;
         let _items = parse(&content);
 
-        let mut items = HashMap::new();
-        let mut crcs = HashMap::new();
+        let mut items: Vec<Item> = Vec::new();
+        let mut synthesized: HashSet<PathBuf> = HashSet::new();
         for _item in _items {
             let _item = _item?;
             if let Kind::Pattern(pattern) = _item.0 {
@@ -43,19 +114,15 @@ impl Attributes {
                 if !path.is_absolute() {
                     path = path::Path::new("/").join(path);
                 }
-                let mut current_path = path.clone();
+
                 let mut attributes = HashMap::new();
-                let mut _type = String::new();
-                let mut _crcs = vec![];
+                let mut crcs = vec![];
                 for line in _item.1 {
                     let line = line?;
                     let name = line.name.as_str();
                     let state = line.state.as_bstr().unwrap_or_default();
-                    if name == KEY_TYPE {
-                        _type = state.to_string();
-                    }
                     if name == KEY_CRCS {
-                        _crcs = state
+                        crcs = state
                             .to_string()
                             .split(',')
                             .map(|s| {
@@ -71,57 +138,95 @@ impl Attributes {
                     }
                     attributes.insert(name.to_string(), state.to_string());
                 }
-                crcs.insert(path.clone(), _crcs);
-                items.insert(path, attributes);
 
-                // process parent directory
-                while let Some(parent) = current_path.parent() {
-                    if parent == Path::new("/") {
+                // Process ancestor directories up to and including the last literal
+                // component before the first glob component, so that e.g.
+                // `/models/*.safetensors type=external` still marks `/models`
+                // itself as holding external content.
+                let path_str = path.to_string_lossy();
+                let segs = split_segments(&path_str);
+                let mut literal_prefix_len = segs.len();
+                for (i, seg) in segs.iter().enumerate() {
+                    if !is_literal_segment(seg) {
+                        literal_prefix_len = i;
                         break;
                     }
-                    let mut attributes = HashMap::new();
-                    if !items.contains_key(parent) {
-                        attributes.insert(KEY_TYPE.to_string(), VAL_EXTERNAL.to_string());
-                        items.insert(parent.to_path_buf(), attributes);
+                }
+                // If the pattern itself has a glob component, its last literal
+                // ancestor (at `literal_prefix_len`) is still a directory to mark,
+                // not the pattern's own path; if the whole pattern is literal,
+                // `literal_prefix_len` equals `segs.len()` and is the path itself,
+                // which is added separately below.
+                let ancestor_upper = if literal_prefix_len == segs.len() {
+                    literal_prefix_len
+                } else {
+                    literal_prefix_len + 1
+                };
+                for i in (1..ancestor_upper).rev() {
+                    let ancestor = PathBuf::from("/").join(segs[..i].join("/"));
+                    if synthesized.insert(ancestor.clone()) {
+                        let mut attrs = HashMap::new();
+                        attrs.insert(KEY_TYPE.to_string(), VAL_EXTERNAL.to_string());
+                        items.push(Item {
+                            pattern: ancestor,
+                            attributes: attrs,
+                            crcs: vec![],
+                            dir_only: false,
+                        });
                     }
-                    current_path = parent.to_path_buf();
                 }
+
+                items.push(Item {
+                    pattern: path,
+                    attributes,
+                    crcs,
+                    dir_only: pattern
+                        .mode
+                        .contains(gix_attributes::glob::pattern::Mode::MUST_BE_DIR),
+                });
             }
         }
 
-        Ok(Attributes { items, crcs })
+        Ok(Attributes { items })
     }
 
     fn check_external(&self, attributes: &HashMap<String, String>) -> bool {
         attributes.get(KEY_TYPE) == Some(&VAL_EXTERNAL.to_string())
     }
 
+    /// Find the last item whose pattern matches `path`, i.e. the item that
+    /// wins according to gitattributes "last match wins" precedence.
+    fn matching_item<P: AsRef<Path>>(&self, path: P) -> Option<&Item> {
+        let path = path.as_ref().to_string_lossy();
+        let path_segs = split_segments(&path);
+        self.items.iter().rev().find(|item| item.matches(&path_segs))
+    }
+
     pub fn is_external<P: AsRef<Path>>(&self, path: P) -> bool {
-        if let Some(attributes) = self.items.get(path.as_ref()) {
-            return self.check_external(attributes);
-        }
-        false
+        self.matching_item(path)
+            .map(|item| self.check_external(&item.attributes))
+            .unwrap_or(false)
     }
 
     pub fn is_prefix_external<P: AsRef<Path>>(&self, target: P) -> bool {
-        self.items
-            .iter()
-            .any(|item| item.0.starts_with(&target) && self.check_external(item.1))
+        self.items.iter().any(|item| {
+            !item.is_dir_pattern()
+                && item.pattern.starts_with(&target)
+                && self.check_external(&item.attributes)
+        })
     }
 
     pub fn get_value<P: AsRef<Path>, K: AsRef<str>>(&self, path: P, key: K) -> Option<String> {
-        if let Some(attributes) = self.items.get(path.as_ref()) {
-            return attributes.get(key.as_ref()).map(|s| s.to_string());
-        }
-        None
+        self.matching_item(path)
+            .and_then(|item| item.attributes.get(key.as_ref()).map(|s| s.to_string()))
     }
 
     pub fn get_values<P: AsRef<Path>>(&self, path: P) -> Option<&HashMap<String, String>> {
-        self.items.get(path.as_ref())
+        self.matching_item(path).map(|item| &item.attributes)
     }
 
     pub fn get_crcs<P: AsRef<Path>>(&self, path: P) -> Option<&Vec<u32>> {
-        self.crcs.get(path.as_ref())
+        self.matching_item(path).map(|item| &item.crcs)
     }
 }
 
@@ -144,46 +249,11 @@ mod tests {
         .unwrap();
 
         let attributes = Attributes::from(file.as_path()).unwrap();
-        let _attributes_base: HashMap<String, String> =
-            [("type".to_string(), "external".to_string())]
-                .iter()
-                .cloned()
-                .collect();
-        let _attributes: HashMap<String, String> = [
-            ("type".to_string(), "external".to_string()),
-            ("crcs".to_string(), "0x1234,0x5678".to_string()),
-        ]
-        .iter()
-        .cloned()
-        .collect();
-
-        let items_map: HashMap<PathBuf, HashMap<String, String>> = vec![
-            Item {
-                pattern: PathBuf::from("/foo"),
-                attributes: _attributes.clone(),
-            },
-            Item {
-                pattern: PathBuf::from("/bar"),
-                attributes: _attributes.clone(),
-            },
-            Item {
-                pattern: PathBuf::from("/models"),
-                attributes: _attributes_base.clone(),
-            },
-            Item {
-                pattern: PathBuf::from("/models/foo"),
-                attributes: _attributes_base.clone(),
-            },
-            Item {
-                pattern: PathBuf::from("/models/foo/bar"),
-                attributes: _attributes_base.clone(),
-            },
-        ]
-        .into_iter()
-        .map(|item| (item.pattern, item.attributes))
-        .collect();
-
-        assert_eq!(attributes.items, items_map);
+        assert!(attributes.is_external("/foo"));
+        assert!(attributes.is_external("/bar"));
+        assert!(attributes.is_external("/models/foo/bar"));
+        assert!(attributes.is_external("/models"));
+        assert!(attributes.is_external("/models/foo"));
         assert_eq!(attributes.get_crcs("/foo"), Some(&vec![0x1234, 0x5678]))
     }
 
@@ -300,12 +370,74 @@ mod tests {
         let item = Item::default();
         assert_eq!(item.pattern, PathBuf::new());
         assert!(item.attributes.is_empty());
+        assert!(item.crcs.is_empty());
     }
 
     #[test]
     fn test_default_attributes() {
         let attributes = Attributes::default();
         assert!(attributes.items.is_empty());
-        assert!(attributes.crcs.is_empty());
+    }
+
+    #[test]
+    fn test_glob_star_does_not_cross_slash() {
+        let file = TempFile::new().unwrap();
+        fs::write(file.as_path(), "/models/*.safetensors type=external").unwrap();
+
+        let attributes = Attributes::from(file.as_path()).unwrap();
+        assert!(attributes.is_external("/models/foo.safetensors"));
+        assert!(!attributes.is_external("/models/sub/foo.safetensors"));
+        // The literal ancestor directory is implicitly marked external too.
+        assert!(attributes.is_external("/models"));
+    }
+
+    #[test]
+    fn test_glob_double_star_crosses_directories() {
+        let file = TempFile::new().unwrap();
+        fs::write(file.as_path(), "**/*.bin crcs=0x1234").unwrap();
+
+        let attributes = Attributes::from(file.as_path()).unwrap();
+        assert_eq!(attributes.get_crcs("/foo.bin"), Some(&vec![0x1234]));
+        assert_eq!(
+            attributes.get_crcs("/a/b/c/foo.bin"),
+            Some(&vec![0x1234])
+        );
+        assert_eq!(attributes.get_crcs("/foo.txt"), None);
+    }
+
+    #[test]
+    fn test_glob_question_mark() {
+        let file = TempFile::new().unwrap();
+        fs::write(file.as_path(), "/data/?ata/** type=external").unwrap();
+
+        let attributes = Attributes::from(file.as_path()).unwrap();
+        assert!(attributes.is_external("/data/data/foo"));
+        assert!(attributes.is_external("/data/xata/foo/bar"));
+        assert!(!attributes.is_external("/data/ata/foo"));
+    }
+
+    #[test]
+    fn test_glob_trailing_slash_is_directory_only() {
+        let file = TempFile::new().unwrap();
+        fs::write(file.as_path(), "/models/ type=external").unwrap();
+
+        let attributes = Attributes::from(file.as_path()).unwrap();
+        assert!(attributes.is_external("/models"));
+        assert!(attributes.is_external("/models/foo.bin"));
+        assert!(attributes.is_external("/models/sub/foo.bin"));
+    }
+
+    #[test]
+    fn test_last_matching_pattern_wins() {
+        let file = TempFile::new().unwrap();
+        fs::write(
+            file.as_path(),
+            "/models/*.safetensors type=external\n/models/keep.safetensors type=internal",
+        )
+        .unwrap();
+
+        let attributes = Attributes::from(file.as_path()).unwrap();
+        assert!(attributes.is_external("/models/other.safetensors"));
+        assert!(!attributes.is_external("/models/keep.safetensors"));
     }
 }
@@ -15,12 +15,28 @@ use nydus_rafs::metadata::layout::v5::RafsV5ChunkInfo;
 use nydus_rafs::metadata::{RafsSuper, RafsSuperConfig};
 use nydus_storage::device::BlobInfo;
 use nydus_utils::digest::{self, RafsDigest};
+use serde::{Deserialize, Serialize};
 
 use crate::Tree;
 
 #[derive(Debug, PartialEq, Eq, Hash, Ord, PartialOrd)]
 pub struct DigestWithBlobIndex(pub RafsDigest, pub u32, pub Option<u32>);
 
+/// Deduplication effectiveness report for a [HashChunkDict], derived from the
+/// per-chunk reference counters maintained by `add_chunk`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DedupStats {
+    /// Number of distinct chunks held by the dictionary.
+    pub unique_chunks: u64,
+    /// Total number of `add_chunk` references recorded, including the first.
+    pub total_references: u64,
+    /// References beyond the first for each chunk, i.e. `total_references - unique_chunks`.
+    pub duplicate_references: u64,
+    /// Sum of `uncompressed_size` for every duplicate reference, i.e. the bytes that
+    /// didn't need to be stored again thanks to deduplication.
+    pub bytes_saved: u64,
+}
+
 /// Trait to manage chunk cache for chunk deduplication.
 pub trait ChunkDict: Sync + Send + 'static {
     /// Add a chunk into the cache.
@@ -143,15 +159,155 @@ impl HashChunkDict {
         &self.m
     }
 
+    /// Drop entries whose reference count falls below `min_references`, optionally
+    /// also capping the dictionary at the `top_n` most-referenced chunks, and prune
+    /// the blob index mapping for any inner index no longer referenced by a live
+    /// chunk. This bounds dictionary growth by keeping only the chunks that have
+    /// actually proven worth deduplicating.
+    pub fn vacuum(&mut self, min_references: u32, top_n: Option<usize>) {
+        self.m
+            .retain(|_, (_, count)| count.load(Ordering::Acquire) >= min_references);
+
+        if let Some(top_n) = top_n {
+            if self.m.len() > top_n {
+                let mut counts: Vec<(RafsDigest, u32)> = self
+                    .m
+                    .iter()
+                    .map(|(digest, (_, count))| (digest.clone(), count.load(Ordering::Acquire)))
+                    .collect();
+                counts.sort_by(|a, b| b.1.cmp(&a.1));
+                let drop: std::collections::HashSet<RafsDigest> = counts
+                    .into_iter()
+                    .skip(top_n)
+                    .map(|(digest, _)| digest)
+                    .collect();
+                self.m.retain(|digest, _| !drop.contains(digest));
+            }
+        }
+
+        let live_blob_indices: std::collections::HashSet<u32> = self
+            .m
+            .values()
+            .map(|(chunk, _)| chunk.blob_index())
+            .collect();
+        self.blob_idx_m
+            .lock()
+            .unwrap()
+            .retain(|inner_idx, _| live_blob_indices.contains(inner_idx));
+    }
+
+    /// Report how effective this dictionary has been at deduplicating chunks.
+    pub fn dedup_stats(&self) -> DedupStats {
+        let mut stats = DedupStats::default();
+
+        for (chunk, count) in self.m.values() {
+            let count = count.load(Ordering::Acquire) as u64;
+            stats.unique_chunks += 1;
+            stats.total_references += count;
+            if count > 1 {
+                stats.duplicate_references += count - 1;
+                stats.bytes_saved += (count - 1) * chunk.uncompressed_size() as u64;
+            }
+        }
+
+        stats
+    }
+
     /// Parse commandline argument for chunk dictionary and load chunks into the dictionary.
     pub fn from_commandline_arg(
         arg: &str,
         config: Arc<ConfigV2>,
         rafs_config: &RafsSuperConfig,
     ) -> Result<Arc<dyn ChunkDict>> {
-        let file_path = parse_chunk_dict_arg(arg)?;
-        HashChunkDict::from_bootstrap_file(&file_path, config, rafs_config)
-            .map(|d| Arc::new(d) as Arc<dyn ChunkDict>)
+        match resolve_dict_arg(arg, config, rafs_config)? {
+            ResolvedDict::Hash(d) => Ok(Arc::new(d) as Arc<dyn ChunkDict>),
+            ResolvedDict::Db(d) => Ok(Arc::new(d) as Arc<dyn ChunkDict>),
+        }
+    }
+
+    /// Compose a dictionary from a manifest file of newline-separated directives:
+    /// - `%include <arg>`: merge every chunk (and blob) of another `--chunk-dict`
+    ///   argument (`bootstrap=`, `db=`, or another `manifest=`) into this dictionary.
+    /// - `%unset <digest>` or `%unset <arg>`: remove a single chunk digest, or every
+    ///   digest referenced by another `--chunk-dict` argument, from the merged result.
+    ///
+    /// Unsets are applied after all includes have been merged, so ordering between
+    /// `%include`/`%unset` lines doesn't matter; blank lines and `#` comments are
+    /// ignored. Each included chunk's blob index is rebased by the offset its
+    /// source blobs are merged in at, so it keeps pointing at the right entry in
+    /// `merged.blobs` instead of colliding with another included dict's indices.
+    pub fn from_manifest_file(
+        path: &Path,
+        config: Arc<ConfigV2>,
+        rafs_config: &RafsSuperConfig,
+    ) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read chunk dict manifest {:?}", path))?;
+
+        let mut merged = HashChunkDict::new(rafs_config.digester);
+        let mut unsets = Vec::new();
+
+        for (lineno, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (directive, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let rest = rest.trim();
+            match directive {
+                "%include" => {
+                    let included =
+                        resolve_dict_arg(rest, config.clone(), rafs_config).with_context(|| {
+                            format!("{:?}:{}: failed to resolve %include {}", path, lineno + 1, rest)
+                        })?;
+                    if included.digester() != merged.digester {
+                        bail!(
+                            "{:?}:{}: %include {} uses a different digest algorithm",
+                            path,
+                            lineno + 1,
+                            rest
+                        );
+                    }
+                    // Rebase each included chunk's blob index by where its source
+                    // blobs land in `merged.blobs`, so `get_blob_by_inner_idx` keeps
+                    // resolving to the right blob after multiple dicts are merged.
+                    let blob_offset = merged.blobs.len() as u32;
+                    merged.blobs.extend(included.blobs());
+                    for (chunk, count) in included.hashmap().values() {
+                        let mut rebased = (**chunk).clone();
+                        rebased.set_blob_index(chunk.blob_index() + blob_offset);
+                        let rebased = Arc::new(rebased);
+                        for _ in 0..count.load(Ordering::Acquire) {
+                            merged.add_chunk(rebased.clone(), merged.digester);
+                        }
+                    }
+                }
+                "%unset" => {
+                    if let Some(digest) = parse_manifest_digest(rest) {
+                        unsets.push(digest);
+                    } else {
+                        let excluded =
+                            resolve_dict_arg(rest, config.clone(), rafs_config).with_context(
+                                || format!("{:?}:{}: failed to resolve %unset {}", path, lineno + 1, rest),
+                            )?;
+                        unsets.extend(excluded.hashmap().keys().cloned());
+                    }
+                }
+                _ => bail!(
+                    "{:?}:{}: unknown chunk dict manifest directive {:?}",
+                    path,
+                    lineno + 1,
+                    directive
+                ),
+            }
+        }
+
+        for digest in unsets {
+            merged.m.remove(&digest);
+        }
+
+        Ok(merged)
     }
 
     /// Load chunks from the RAFS filesystem into the chunk dictionary.
@@ -208,6 +364,137 @@ impl HashChunkDict {
     }
 }
 
+/// On-disk representation of a single dictionary entry, as persisted by [DbChunkDict].
+#[derive(Serialize, Deserialize)]
+struct DbEntry {
+    chunk: ChunkWrapper,
+    refcount: u32,
+}
+
+/// An implementation of [ChunkDict] backed by an embedded `sled` key-value store.
+///
+/// Unlike [HashChunkDict], which is rebuilt from a bootstrap on every invocation, a
+/// `DbChunkDict` persists its digest -> chunk/refcount entries to disk, so it can be
+/// grown incrementally and reused across many image builds without re-parsing a
+/// bootstrap each time. The persisted entries are loaded into memory when the
+/// dictionary is opened and kept in sync on every `add_chunk`, so lookups during a
+/// build stay as fast as [HashChunkDict].
+pub struct DbChunkDict {
+    m: HashMap<RafsDigest, (Arc<ChunkWrapper>, AtomicU32)>,
+    blobs: Vec<Arc<BlobInfo>>,
+    blob_idx_m: Mutex<BTreeMap<u32, u32>>,
+    digester: digest::Algorithm,
+    db: sled::Db,
+}
+
+impl ChunkDict for DbChunkDict {
+    fn add_chunk(&mut self, chunk: Arc<ChunkWrapper>, digester: digest::Algorithm) {
+        if self.digester != digester {
+            return;
+        }
+
+        let refcount = if let Some(e) = self.m.get(chunk.id()) {
+            e.1.fetch_add(1, Ordering::AcqRel) + 1
+        } else {
+            self.m
+                .insert(chunk.id().to_owned(), (chunk.clone(), AtomicU32::new(1)));
+            1
+        };
+
+        let entry = DbEntry {
+            chunk: (*chunk).clone(),
+            refcount,
+        };
+        match bincode::serialize(&entry) {
+            Ok(bytes) => {
+                if let Err(e) = self.db.insert(chunk.id().data, bytes) {
+                    warn!("failed to persist chunk dict entry: {}", e);
+                }
+            }
+            Err(e) => warn!("failed to encode chunk dict entry: {}", e),
+        }
+    }
+
+    fn get_chunk(&self, digest: &RafsDigest, uncompressed_size: u32) -> Option<&Arc<ChunkWrapper>> {
+        if let Some((chunk, _)) = self.m.get(digest) {
+            if chunk.uncompressed_size() == 0 || chunk.uncompressed_size() == uncompressed_size {
+                return Some(chunk);
+            }
+        }
+        None
+    }
+
+    fn get_blobs(&self) -> Vec<Arc<BlobInfo>> {
+        self.blobs.clone()
+    }
+
+    fn get_blob_by_inner_idx(&self, idx: u32) -> Option<&Arc<BlobInfo>> {
+        self.blobs.get(idx as usize)
+    }
+
+    fn set_real_blob_idx(&self, inner_idx: u32, out_idx: u32) {
+        self.blob_idx_m.lock().unwrap().insert(inner_idx, out_idx);
+    }
+
+    fn get_real_blob_idx(&self, inner_idx: u32) -> Option<u32> {
+        self.blob_idx_m.lock().unwrap().get(&inner_idx).copied()
+    }
+
+    fn digester(&self) -> digest::Algorithm {
+        self.digester
+    }
+}
+
+impl DbChunkDict {
+    /// Open (creating if necessary) a persistent chunk dictionary database at `path`,
+    /// loading any previously persisted entries into memory.
+    pub fn open(path: impl AsRef<Path>, digester: digest::Algorithm) -> Result<Self> {
+        let db = sled::open(path.as_ref())
+            .with_context(|| format!("failed to open chunk dict db {:?}", path.as_ref()))?;
+
+        let mut m = HashMap::new();
+        for item in db.iter() {
+            let (key, value) = item.context("failed to iterate chunk dict db")?;
+            let entry: DbEntry = bincode::deserialize(&value)
+                .context("failed to decode persisted chunk dict entry")?;
+            let mut data = [0u8; 32];
+            if key.len() != data.len() {
+                bail!("corrupted chunk dict db: unexpected key length {}", key.len());
+            }
+            data.copy_from_slice(&key);
+            let digest = RafsDigest { data };
+            m.insert(
+                digest,
+                (Arc::new(entry.chunk), AtomicU32::new(entry.refcount)),
+            );
+        }
+
+        Ok(DbChunkDict {
+            m,
+            blobs: Vec::new(),
+            blob_idx_m: Mutex::new(BTreeMap::new()),
+            digester,
+            db,
+        })
+    }
+
+    /// Get an immutable reference to the internal in-memory index.
+    pub fn hashmap(&self) -> &HashMap<RafsDigest, (Arc<ChunkWrapper>, AtomicU32)> {
+        &self.m
+    }
+}
+
+/// Parsed form of a `--chunk-dict` commandline argument.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ChunkDictArg {
+    /// Load chunks from a RAFS bootstrap file.
+    Bootstrap(PathBuf),
+    /// Load chunks from (and persist chunks into) an on-disk chunk dictionary database.
+    Db(PathBuf),
+    /// Compose a dictionary from a manifest file of `%include`/`%unset` directives.
+    Manifest(PathBuf),
+}
+
 /// Parse a chunk dictionary argument string.
 ///
 /// # Argument
@@ -219,8 +506,10 @@ impl HashChunkDict {
 ///     bootstrap=image.boot
 ///     image.boot
 ///     ~/image/image.boot
-///     boltdb=/var/db/dict.db (not supported yet)
-pub fn parse_chunk_dict_arg(arg: &str) -> Result<PathBuf> {
+///     boltdb=/var/db/dict.db
+///     db=/var/db/dict.db
+///     manifest=/var/db/dict.manifest
+pub fn parse_chunk_dict_arg(arg: &str) -> Result<ChunkDictArg> {
     let (file_type, file_path) = match arg.find('=') {
         None => ("bootstrap", arg),
         Some(idx) => (&arg[0..idx], &arg[idx + 1..]),
@@ -229,11 +518,74 @@ pub fn parse_chunk_dict_arg(arg: &str) -> Result<PathBuf> {
     debug!("parse chunk dict argument {}={}", file_type, file_path);
 
     match file_type {
-        "bootstrap" => Ok(PathBuf::from(file_path)),
+        "bootstrap" => Ok(ChunkDictArg::Bootstrap(PathBuf::from(file_path))),
+        "boltdb" | "db" => Ok(ChunkDictArg::Db(PathBuf::from(file_path))),
+        "manifest" => Ok(ChunkDictArg::Manifest(PathBuf::from(file_path))),
         _ => bail!("invalid chunk dict type {}", file_type),
     }
 }
 
+/// A dictionary resolved from a single `--chunk-dict` argument, kept concrete (rather
+/// than boxed as `dyn ChunkDict`) so manifest resolution can enumerate its entries via
+/// `hashmap()`, which isn't part of the object-safe [ChunkDict] trait.
+enum ResolvedDict {
+    Hash(HashChunkDict),
+    Db(DbChunkDict),
+}
+
+impl ResolvedDict {
+    fn hashmap(&self) -> &HashMap<RafsDigest, (Arc<ChunkWrapper>, AtomicU32)> {
+        match self {
+            ResolvedDict::Hash(d) => d.hashmap(),
+            ResolvedDict::Db(d) => d.hashmap(),
+        }
+    }
+
+    fn blobs(&self) -> Vec<Arc<BlobInfo>> {
+        match self {
+            ResolvedDict::Hash(d) => d.blobs.clone(),
+            ResolvedDict::Db(d) => d.blobs.clone(),
+        }
+    }
+
+    fn digester(&self) -> digest::Algorithm {
+        match self {
+            ResolvedDict::Hash(d) => d.digester,
+            ResolvedDict::Db(d) => d.digester,
+        }
+    }
+}
+
+fn resolve_dict_arg(
+    arg: &str,
+    config: Arc<ConfigV2>,
+    rafs_config: &RafsSuperConfig,
+) -> Result<ResolvedDict> {
+    match parse_chunk_dict_arg(arg)? {
+        ChunkDictArg::Bootstrap(path) => {
+            HashChunkDict::from_bootstrap_file(&path, config, rafs_config).map(ResolvedDict::Hash)
+        }
+        ChunkDictArg::Db(path) => {
+            DbChunkDict::open(&path, rafs_config.digester).map(ResolvedDict::Db)
+        }
+        ChunkDictArg::Manifest(path) => {
+            HashChunkDict::from_manifest_file(&path, config, rafs_config).map(ResolvedDict::Hash)
+        }
+    }
+}
+
+/// Decode a 64 hex-character chunk digest, as used by `%unset <digest>` directives.
+fn parse_manifest_digest(token: &str) -> Option<RafsDigest> {
+    if token.len() != 64 || !token.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let mut data = [0u8; 32];
+    for (i, byte) in data.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&token[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(RafsDigest { data })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -278,22 +630,156 @@ mod tests {
         assert_eq!(dict.get_real_blob_idx(1), None);
     }
 
+    fn rafs_v5_fixture_path() -> PathBuf {
+        let root_dir = &std::env::var("CARGO_MANIFEST_DIR").expect("$CARGO_MANIFEST_DIR");
+        let mut source_path = PathBuf::from(root_dir);
+        source_path.push("../tests/texture/bootstrap/rafs-v5.boot");
+        source_path
+    }
+
+    fn blake3_rafs_config() -> RafsSuperConfig {
+        RafsSuperConfig {
+            version: RafsVersion::V5,
+            compressor: compress::Algorithm::Lz4Block,
+            digester: digest::Algorithm::Blake3,
+            chunk_size: 0x100000,
+            batch_size: 0,
+            explicit_uidgid: true,
+            is_tarfs_mode: false,
+        }
+    }
+
+    #[test]
+    fn test_chunk_dict_manifest_include() {
+        let fixture = rafs_v5_fixture_path();
+        let rafs_config = blake3_rafs_config();
+
+        let manifest_path = std::env::temp_dir().join(format!(
+            "nydus-chunk-dict-manifest-test-{}-{}.manifest",
+            std::process::id(),
+            line!()
+        ));
+        std::fs::write(
+            &manifest_path,
+            format!(
+                "# merge a single bootstrap\n%include bootstrap={}\n",
+                fixture.display()
+            ),
+        )
+        .unwrap();
+
+        let dict = HashChunkDict::from_manifest_file(
+            &manifest_path,
+            Arc::new(ConfigV2::default()),
+            &rafs_config,
+        )
+        .unwrap();
+
+        assert_eq!(dict.get_blobs().len(), 18);
+        assert!(!dict.hashmap().is_empty());
+
+        std::fs::remove_file(&manifest_path).ok();
+    }
+
+    #[test]
+    fn test_chunk_dict_manifest_unset_digest() {
+        let fixture = rafs_v5_fixture_path();
+        let rafs_config = blake3_rafs_config();
+
+        let plain = HashChunkDict::from_bootstrap_file(
+            &fixture,
+            Arc::new(ConfigV2::default()),
+            &rafs_config,
+        )
+        .unwrap();
+        let (digest, _) = plain.hashmap().iter().next().expect("fixture has chunks");
+        let digest_hex = digest
+            .data
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+
+        let manifest_path = std::env::temp_dir().join(format!(
+            "nydus-chunk-dict-manifest-unset-test-{}-{}.manifest",
+            std::process::id(),
+            line!()
+        ));
+        std::fs::write(
+            &manifest_path,
+            format!(
+                "%include bootstrap={}\n%unset {}\n",
+                fixture.display(),
+                digest_hex
+            ),
+        )
+        .unwrap();
+
+        let dict = HashChunkDict::from_manifest_file(
+            &manifest_path,
+            Arc::new(ConfigV2::default()),
+            &rafs_config,
+        )
+        .unwrap();
+
+        assert_eq!(dict.hashmap().len(), plain.hashmap().len() - 1);
+        assert!(!dict.hashmap().contains_key(digest));
+
+        std::fs::remove_file(&manifest_path).ok();
+    }
+
+    #[test]
+    fn test_chunk_dict_manifest_rejects_unknown_directive() {
+        let rafs_config = blake3_rafs_config();
+        let manifest_path = std::env::temp_dir().join(format!(
+            "nydus-chunk-dict-manifest-bad-test-{}-{}.manifest",
+            std::process::id(),
+            line!()
+        ));
+        std::fs::write(&manifest_path, "%frobnicate something\n").unwrap();
+
+        let result = HashChunkDict::from_manifest_file(
+            &manifest_path,
+            Arc::new(ConfigV2::default()),
+            &rafs_config,
+        );
+        assert!(result.is_err());
+
+        std::fs::remove_file(&manifest_path).ok();
+    }
+
+    #[test]
+    fn test_parse_manifest_digest() {
+        let hex = "a".repeat(64);
+        assert!(parse_manifest_digest(&hex).is_some());
+        assert!(parse_manifest_digest("not-a-digest").is_none());
+        assert!(parse_manifest_digest(&"a".repeat(63)).is_none());
+    }
+
     #[test]
     fn test_parse_chunk_dict_arg() {
         // Test with bootstrap type
         let result = parse_chunk_dict_arg("bootstrap=/path/to/file").unwrap();
-        assert_eq!(result, PathBuf::from("/path/to/file"));
+        assert_eq!(result, ChunkDictArg::Bootstrap(PathBuf::from("/path/to/file")));
 
         // Test without type prefix (defaults to bootstrap)
         let result = parse_chunk_dict_arg("/path/to/file").unwrap();
-        assert_eq!(result, PathBuf::from("/path/to/file"));
+        assert_eq!(result, ChunkDictArg::Bootstrap(PathBuf::from("/path/to/file")));
 
         // Test with relative path
         let result = parse_chunk_dict_arg("~/image/image.boot").unwrap();
-        assert_eq!(result, PathBuf::from("~/image/image.boot"));
+        assert_eq!(
+            result,
+            ChunkDictArg::Bootstrap(PathBuf::from("~/image/image.boot"))
+        );
+
+        // Test with the boltdb/db persistent backend prefixes
+        let result = parse_chunk_dict_arg("boltdb=/var/db/dict.db").unwrap();
+        assert_eq!(result, ChunkDictArg::Db(PathBuf::from("/var/db/dict.db")));
+        let result = parse_chunk_dict_arg("db=/var/db/dict.db").unwrap();
+        assert_eq!(result, ChunkDictArg::Db(PathBuf::from("/var/db/dict.db")));
 
         // Test with invalid type
-        let result = parse_chunk_dict_arg("boltdb=/var/db/dict.db");
+        let result = parse_chunk_dict_arg("unknown=/var/db/dict.db");
         assert!(result.is_err());
         assert!(result
             .unwrap_err()
@@ -301,6 +787,33 @@ mod tests {
             .contains("invalid chunk dict type"));
     }
 
+    #[test]
+    fn test_db_chunk_dict_persists_across_reopen() {
+        let tmp_dir = std::env::temp_dir().join(format!(
+            "nydus-chunk-dict-db-test-{}-{}",
+            std::process::id(),
+            line!()
+        ));
+
+        {
+            let mut dict = DbChunkDict::open(&tmp_dir, digest::Algorithm::Sha256).unwrap();
+            let chunk = Arc::new(ChunkWrapper::new(RafsVersion::V5));
+            dict.add_chunk(chunk.clone(), digest::Algorithm::Sha256);
+            assert!(dict.get_chunk(chunk.id(), 0).is_some());
+
+            // Chunks added with a mismatched digester must be ignored.
+            dict.add_chunk(chunk.clone(), digest::Algorithm::Blake3);
+            assert_eq!(dict.hashmap().len(), 1);
+        }
+
+        // Reopening the same path must recover the previously persisted entry.
+        let reopened = DbChunkDict::open(&tmp_dir, digest::Algorithm::Sha256).unwrap();
+        assert_eq!(reopened.hashmap().len(), 1);
+        assert_eq!(reopened.digester(), digest::Algorithm::Sha256);
+
+        std::fs::remove_dir_all(&tmp_dir).ok();
+    }
+
     #[test]
     fn test_hash_chunk_dict_new() {
         let dict = HashChunkDict::new(digest::Algorithm::Blake3);
@@ -344,6 +857,65 @@ mod tests {
         assert_eq!(dict.hashmap().len(), 1);
     }
 
+    #[test]
+    fn test_hash_chunk_dict_dedup_stats() {
+        let mut dict = HashChunkDict::new(digest::Algorithm::Sha256);
+
+        // An empty dictionary has nothing to report.
+        let stats = dict.dedup_stats();
+        assert_eq!(stats.unique_chunks, 0);
+        assert_eq!(stats.total_references, 0);
+        assert_eq!(stats.duplicate_references, 0);
+        assert_eq!(stats.bytes_saved, 0);
+
+        let chunk = Arc::new(ChunkWrapper::new(RafsVersion::V5));
+        dict.add_chunk(chunk.clone(), digest::Algorithm::Sha256);
+        dict.add_chunk(chunk.clone(), digest::Algorithm::Sha256);
+        dict.add_chunk(chunk.clone(), digest::Algorithm::Sha256);
+
+        let stats = dict.dedup_stats();
+        assert_eq!(stats.unique_chunks, 1);
+        assert_eq!(stats.total_references, 3);
+        assert_eq!(stats.duplicate_references, 2);
+        assert_eq!(stats.bytes_saved, 2 * chunk.uncompressed_size() as u64);
+    }
+
+    #[test]
+    fn test_hash_chunk_dict_vacuum_by_min_references() {
+        let mut dict = HashChunkDict::new(digest::Algorithm::Sha256);
+        let chunk = Arc::new(ChunkWrapper::new(RafsVersion::V5));
+        dict.add_chunk(chunk.clone(), digest::Algorithm::Sha256);
+        dict.add_chunk(chunk.clone(), digest::Algorithm::Sha256);
+
+        // The chunk has 2 references, so a threshold of 3 should drop it.
+        dict.vacuum(3, None);
+        assert_eq!(dict.hashmap().len(), 0);
+
+        dict.add_chunk(chunk.clone(), digest::Algorithm::Sha256);
+        dict.add_chunk(chunk.clone(), digest::Algorithm::Sha256);
+
+        // A threshold at or below the reference count keeps the chunk.
+        dict.vacuum(2, None);
+        assert_eq!(dict.hashmap().len(), 1);
+    }
+
+    #[test]
+    fn test_hash_chunk_dict_vacuum_prunes_dead_blob_index_mapping() {
+        let mut dict = HashChunkDict::new(digest::Algorithm::Sha256);
+        let chunk = Arc::new(ChunkWrapper::new(RafsVersion::V5));
+        dict.add_chunk(chunk.clone(), digest::Algorithm::Sha256);
+
+        let live_idx = chunk.blob_index();
+        let dead_idx = live_idx + 1;
+        dict.set_real_blob_idx(live_idx, 100);
+        dict.set_real_blob_idx(dead_idx, 200);
+
+        dict.vacuum(0, None);
+
+        assert_eq!(dict.get_real_blob_idx(live_idx), Some(100));
+        assert_eq!(dict.get_real_blob_idx(dead_idx), None);
+    }
+
     #[test]
     fn test_hash_chunk_dict_get_chunk() {
         let mut dict = HashChunkDict::new(digest::Algorithm::Sha256);
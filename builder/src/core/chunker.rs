@@ -0,0 +1,316 @@
+// Copyright 2020 Ant Group. All rights reserved.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Content-defined chunking (FastCDC) as an alternative to fixed-size cut points.
+//!
+//! Fixed-size chunking shifts every downstream chunk boundary after a single byte is
+//! inserted or removed, which destroys deduplication across image versions. FastCDC
+//! instead rolls a gear hash over the stream and declares a cut whenever the rolling
+//! fingerprint satisfies a bitmask, so boundaries are stable across edits that don't
+//! touch them. This module only produces chunk boundaries and digests; it feeds the
+//! existing [ChunkDict] `add_chunk`/`get_chunk` path the same way fixed-size chunks do.
+
+use std::sync::Arc;
+
+use nydus_rafs::metadata::chunk::ChunkWrapper;
+use nydus_utils::digest::{self, RafsDigest};
+
+use crate::core::chunk_dict::ChunkDict;
+
+const fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+const fn gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut seed = 0x9E37_79B9_7F4A_7C15u64;
+    let mut i = 0;
+    while i < 256 {
+        seed = splitmix64(seed);
+        table[i] = seed;
+        i += 1;
+    }
+    table
+}
+
+/// Gear hashing lookup table, used to roll a fingerprint over the input stream.
+const GEAR: [u64; 256] = gear_table();
+
+/// Size parameters controlling FastCDC content-defined chunking.
+#[derive(Debug, Clone, Copy)]
+pub struct FastCdcConfig {
+    /// Minimum chunk size; no cut point is considered below this length.
+    pub min_size: usize,
+    /// Target average chunk size.
+    pub avg_size: usize,
+    /// Maximum chunk size; a cut is forced at this length regardless of the hash.
+    pub max_size: usize,
+}
+
+impl Default for FastCdcConfig {
+    /// Defaults roughly matching a typical RAFS chunk size of 1MiB, halved/doubled
+    /// for the min/max bounds.
+    fn default() -> Self {
+        FastCdcConfig {
+            min_size: 512 * 1024,
+            avg_size: 1024 * 1024,
+            max_size: 2048 * 1024,
+        }
+    }
+}
+
+/// A single content-defined chunk boundary, as a `[offset, offset + len)` byte range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CdcChunk {
+    pub offset: usize,
+    pub len: usize,
+}
+
+/// Cuts a byte stream into variable-size chunks using FastCDC with normalized chunking.
+///
+/// Normalized chunking applies a stricter mask (more one-bits, so a cut is less likely)
+/// for bytes below the target average size, and a looser mask (fewer one-bits, so a cut
+/// is more likely) once past it, which concentrates chunk sizes around the average
+/// instead of following the geometric distribution of plain content-defined chunking.
+pub struct FastCdcChunker {
+    config: FastCdcConfig,
+    mask_s: u64,
+    mask_l: u64,
+}
+
+impl FastCdcChunker {
+    /// Create a chunker with explicit min/avg/max size parameters.
+    pub fn new(config: FastCdcConfig) -> Self {
+        let bits = (config.avg_size.max(1) as f64).log2().round() as u32;
+        let mask_s = (1u64 << (bits + 1).min(63)) - 1;
+        let mask_l = (1u64 << bits.saturating_sub(1)) - 1;
+        FastCdcChunker {
+            config,
+            mask_s,
+            mask_l,
+        }
+    }
+
+    /// Create a chunker using [FastCdcConfig::default] size parameters.
+    pub fn with_default_config() -> Self {
+        Self::new(FastCdcConfig::default())
+    }
+
+    /// Cut `data` into a sequence of content-defined chunks covering the whole buffer.
+    pub fn cut(&self, data: &[u8]) -> Vec<CdcChunk> {
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        while start < data.len() {
+            let len = self.next_cut(&data[start..]);
+            chunks.push(CdcChunk { offset: start, len });
+            start += len;
+        }
+        chunks
+    }
+
+    /// Cut `data` with FastCDC and look up each resulting chunk's digest in `dict`,
+    /// so the caller can skip writing chunks that are already known.
+    pub fn cut_and_dedup(
+        &self,
+        data: &[u8],
+        digester: digest::Algorithm,
+        dict: &dyn ChunkDict,
+    ) -> Vec<(CdcChunk, RafsDigest, Option<Arc<ChunkWrapper>>)> {
+        self.cut(data)
+            .into_iter()
+            .map(|c| {
+                let digest = RafsDigest::from_buf(&data[c.offset..c.offset + c.len], digester);
+                let cached = dict.get_chunk(&digest, c.len as u32).cloned();
+                (c, digest, cached)
+            })
+            .collect()
+    }
+
+    /// Determine the length of the next chunk at the start of `data`.
+    fn next_cut(&self, data: &[u8]) -> usize {
+        let min = self.config.min_size.min(data.len());
+        let avg = self.config.avg_size.min(data.len());
+        let max = self.config.max_size.min(data.len());
+
+        if data.len() <= min {
+            return data.len();
+        }
+
+        let mut fp: u64 = 0;
+        let mut i = min;
+        while i < avg {
+            fp = (fp << 1).wrapping_add(GEAR[data[i] as usize]);
+            if fp & self.mask_s == 0 {
+                return i + 1;
+            }
+            i += 1;
+        }
+        while i < max {
+            fp = (fp << 1).wrapping_add(GEAR[data[i] as usize]);
+            if fp & self.mask_l == 0 {
+                return i + 1;
+            }
+            i += 1;
+        }
+        max
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> FastCdcConfig {
+        FastCdcConfig {
+            min_size: 64,
+            avg_size: 256,
+            max_size: 1024,
+        }
+    }
+
+    fn pseudo_random_data(len: usize, seed: u64) -> Vec<u8> {
+        let mut data = Vec::with_capacity(len);
+        let mut x = seed;
+        for _ in 0..len {
+            x = splitmix64(x);
+            data.push((x & 0xff) as u8);
+        }
+        data
+    }
+
+    #[test]
+    fn test_cut_covers_whole_buffer() {
+        let chunker = FastCdcChunker::new(test_config());
+        let data = pseudo_random_data(10_000, 1);
+        let chunks = chunker.cut(&data);
+
+        assert!(!chunks.is_empty());
+        let mut offset = 0;
+        for c in &chunks {
+            assert_eq!(c.offset, offset);
+            assert!(c.len > 0);
+            offset += c.len;
+        }
+        assert_eq!(offset, data.len());
+    }
+
+    #[test]
+    fn test_cut_respects_min_and_max_size() {
+        let chunker = FastCdcChunker::new(test_config());
+        let data = pseudo_random_data(10_000, 2);
+        let chunks = chunker.cut(&data);
+
+        for (idx, c) in chunks.iter().enumerate() {
+            // The final chunk may be shorter than min_size since it's just whatever
+            // bytes remain at the end of the stream.
+            if idx + 1 != chunks.len() {
+                assert!(c.len >= test_config().min_size);
+            }
+            assert!(c.len <= test_config().max_size);
+        }
+    }
+
+    #[test]
+    fn test_small_input_is_a_single_chunk() {
+        let chunker = FastCdcChunker::new(test_config());
+        let data = pseudo_random_data(10, 3);
+        let chunks = chunker.cut(&data);
+
+        assert_eq!(chunks, vec![CdcChunk { offset: 0, len: 10 }]);
+    }
+
+    #[test]
+    fn test_cut_is_deterministic() {
+        let chunker = FastCdcChunker::new(test_config());
+        let data = pseudo_random_data(5_000, 4);
+
+        assert_eq!(chunker.cut(&data), chunker.cut(&data));
+    }
+
+    #[test]
+    fn test_insertion_only_shifts_nearby_boundaries() {
+        let chunker = FastCdcChunker::new(test_config());
+        let data = pseudo_random_data(20_000, 5);
+
+        let mut edited = data.clone();
+        edited.splice(10_000..10_000, pseudo_random_data(3, 42));
+
+        let before = chunker.cut(&data);
+        let after = chunker.cut(&edited);
+
+        // Boundaries well before the insertion point must be unaffected.
+        let unaffected_before: Vec<_> = before
+            .iter()
+            .map(|c| c.offset)
+            .take_while(|&o| o < 9_000)
+            .collect();
+        let unaffected_after: Vec<_> = after
+            .iter()
+            .map(|c| c.offset)
+            .take_while(|&o| o < 9_000)
+            .collect();
+        assert_eq!(unaffected_before, unaffected_after);
+
+        // Boundaries well after it are the same ones, shifted by the 3 inserted bytes.
+        let resynced_before: Vec<_> = before
+            .iter()
+            .map(|c| c.offset + 3)
+            .filter(|&o| o > 11_000)
+            .collect();
+        let resynced_after: Vec<_> = after
+            .iter()
+            .map(|c| c.offset)
+            .filter(|&o| o > 11_000)
+            .collect();
+        assert_eq!(resynced_before, resynced_after);
+    }
+
+    /// A [ChunkDict] test double that reports every digest as already known, so
+    /// `cut_and_dedup`'s plumbing can be verified without depending on how a real
+    /// dictionary maps a digest to a stored [ChunkWrapper].
+    struct AlwaysHitDict(Arc<ChunkWrapper>);
+
+    impl ChunkDict for AlwaysHitDict {
+        fn add_chunk(&mut self, _chunk: Arc<ChunkWrapper>, _digester: digest::Algorithm) {}
+
+        fn get_chunk(&self, _digest: &RafsDigest, _uncompressed_size: u32) -> Option<&Arc<ChunkWrapper>> {
+            Some(&self.0)
+        }
+
+        fn get_blobs(&self) -> Vec<Arc<nydus_storage::device::BlobInfo>> {
+            Vec::new()
+        }
+
+        fn get_blob_by_inner_idx(&self, _idx: u32) -> Option<&Arc<nydus_storage::device::BlobInfo>> {
+            None
+        }
+
+        fn set_real_blob_idx(&self, _inner_idx: u32, _out_idx: u32) {}
+
+        fn get_real_blob_idx(&self, _inner_idx: u32) -> Option<u32> {
+            None
+        }
+
+        fn digester(&self) -> digest::Algorithm {
+            digest::Algorithm::Sha256
+        }
+    }
+
+    #[test]
+    fn test_cut_and_dedup_reports_dict_hits() {
+        use nydus_rafs::metadata::RafsVersion;
+
+        let chunker = FastCdcChunker::new(test_config());
+        let data = pseudo_random_data(2_000, 6);
+        let dict = AlwaysHitDict(Arc::new(ChunkWrapper::new(RafsVersion::V5)));
+
+        let results = chunker.cut_and_dedup(&data, digest::Algorithm::Sha256, &dict);
+
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|(_, _, hit)| hit.is_some()));
+    }
+}
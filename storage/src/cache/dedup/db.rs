@@ -0,0 +1,365 @@
+// Copyright (C) 2022-2023 Alibaba Cloud. All rights reserved.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, OptionalExtension};
+
+use super::CasError;
+
+type Result<T> = std::result::Result<T, CasError>;
+
+/// Statistics about a single garbage collection pass.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GcStats {
+    pub reclaimed_chunks: u64,
+    pub reclaimed_blobs: u64,
+    /// Total recorded size of the blobs reclaimed by this sweep, so callers
+    /// can log how much disk space a GC pass actually freed.
+    pub reclaimed_bytes: u64,
+}
+
+/// Persistence backend for the CAS dedup index.
+///
+/// `CasMgr` talks to the store exclusively through this trait, so the
+/// default Sqlite-backed [`CasDb`] can be swapped for an alternative
+/// implementation (a remote/shared index, an in-memory store for tests,
+/// ...) without touching `dedup_chunk`/`record_chunk`.
+pub trait CasStore: Send + Sync {
+    fn get_chunk_info(&self, chunk_id: &str) -> Result<Option<(String, u64)>>;
+    fn add_blob(&self, path: &str) -> Result<i64>;
+    fn add_chunk(&self, chunk_id: &str, offset: u64, path: &str) -> Result<()>;
+    fn add_chunks_batch(&self, entries: &[(String, String, u64)]) -> Result<()>;
+    fn touch_chunk(&self, chunk_id: &str) -> Result<()>;
+    fn delete_chunk(&self, chunk_id: &str) -> Result<()>;
+    fn delete_blobs(&self, paths: &[String]) -> Result<()>;
+    fn get_all_blobs(&self) -> Result<Vec<(i64, String)>>;
+    fn mark_blob_live(&self, path: &str) -> Result<()>;
+    fn sweep(&self, cutoff: i64) -> Result<GcStats>;
+    fn total_bytes(&self) -> Result<u64>;
+    fn evict_lru_blob(&self) -> Result<Option<(String, u64)>>;
+}
+
+/// Sqlite backed persistent storage for the content-addressable dedup cache.
+///
+/// A single global lock, `gc_lock`, serializes the GC sweep and LRU eviction
+/// against each other. Inserts don't take it: a chunk row inserted or touched
+/// after the sweep's cutoff is kept because its `last_used` is not older than
+/// the cutoff.
+pub struct CasDb {
+    pool: Pool<SqliteConnectionManager>,
+    gc_lock: Mutex<()>,
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+impl CasDb {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let mgr = SqliteConnectionManager::file(path);
+        let pool = Pool::new(mgr).map_err(CasError::R2D2)?;
+        let conn = pool.get().map_err(CasError::R2D2)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS blobs (
+                id   INTEGER PRIMARY KEY AUTOINCREMENT,
+                path TEXT NOT NULL UNIQUE,
+                size INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE TABLE IF NOT EXISTS chunks (
+                chunk_id  TEXT PRIMARY KEY,
+                blob_id   INTEGER NOT NULL,
+                offset    INTEGER NOT NULL,
+                last_used INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_chunks_blob_id ON chunks(blob_id);
+            CREATE INDEX IF NOT EXISTS idx_chunks_last_used ON chunks(last_used);",
+        )?;
+
+        Ok(CasDb {
+            pool,
+            gc_lock: Mutex::new(()),
+        })
+    }
+
+    /// Insert the blob if it doesn't already exist, returning its row id.
+    ///
+    /// The blob's on-disk size is recorded at insertion time so the total
+    /// occupied space can be tracked without re-stat'ing every file.
+    pub fn add_blob(&self, path: &str) -> Result<i64> {
+        let conn = self.pool.get()?;
+        let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        conn.execute(
+            "INSERT OR IGNORE INTO blobs (path, size) VALUES (?1, ?2)",
+            params![path, size as i64],
+        )?;
+        let id = conn.query_row(
+            "SELECT id FROM blobs WHERE path = ?1",
+            params![path],
+            |row| row.get(0),
+        )?;
+        Ok(id)
+    }
+
+    /// Sum of the recorded size of every blob tracked by the database.
+    pub fn total_bytes(&self) -> Result<u64> {
+        let conn = self.pool.get()?;
+        let total: i64 =
+            conn.query_row("SELECT COALESCE(SUM(size), 0) FROM blobs", [], |row| {
+                row.get(0)
+            })?;
+        Ok(total as u64)
+    }
+
+    /// Evict the least-recently-used blob, i.e. the one whose most recently
+    /// touched chunk is the oldest (blobs with no chunks at all count as
+    /// never used and are evicted first). Returns the evicted blob's path
+    /// and size, or `None` if there are no blobs to evict.
+    ///
+    /// Takes `gc_lock`, the same lock `sweep` holds, so a disk-budget eviction
+    /// never interleaves with a sweep or with another eviction (two evictions
+    /// can't both pick the same "LRU" blob). `mark_blob_live` doesn't take the
+    /// lock.
+    pub fn evict_lru_blob(&self) -> Result<Option<(String, u64)>> {
+        let _guard = self.gc_lock.lock().unwrap();
+        let conn = self.pool.get()?;
+        let victim = conn
+            .query_row(
+                "SELECT blobs.path, blobs.size FROM blobs
+                 LEFT JOIN chunks ON chunks.blob_id = blobs.id
+                 GROUP BY blobs.id
+                 ORDER BY COALESCE(MAX(chunks.last_used), 0) ASC
+                 LIMIT 1",
+                [],
+                |row| {
+                    let path: String = row.get(0)?;
+                    let size: i64 = row.get(1)?;
+                    Ok((path, size as u64))
+                },
+            )
+            .optional()?;
+
+        if let Some((path, _)) = &victim {
+            conn.execute(
+                "DELETE FROM chunks WHERE blob_id IN (SELECT id FROM blobs WHERE path = ?1)",
+                params![path],
+            )?;
+            conn.execute("DELETE FROM blobs WHERE path = ?1", params![path])?;
+        }
+
+        Ok(victim)
+    }
+
+    /// Record a chunk, touching its `last_used` timestamp to now.
+    pub fn add_chunk(&self, chunk_id: &str, offset: u64, path: &str) -> Result<()> {
+        let conn = self.pool.get()?;
+        let blob_id: i64 = conn.query_row(
+            "SELECT id FROM blobs WHERE path = ?1",
+            params![path],
+            |row| row.get(0),
+        )?;
+        conn.execute(
+            "INSERT INTO chunks (chunk_id, blob_id, offset, last_used) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(chunk_id) DO UPDATE SET blob_id = excluded.blob_id, offset = excluded.offset, last_used = excluded.last_used",
+            params![chunk_id, blob_id, offset as i64, now()],
+        )?;
+        Ok(())
+    }
+
+    /// Record many chunks in a single explicit transaction, reusing prepared
+    /// statements and deduplicating blob-path inserts across the batch.
+    ///
+    /// This is orders of magnitude faster than calling `add_blob`/`add_chunk`
+    /// once per entry, each of which is its own implicit transaction.
+    pub fn add_chunks_batch(&self, entries: &[(String, String, u64)]) -> Result<()> {
+        let mut conn = self.pool.get()?;
+        let tx = conn.transaction().map_err(CasError::Db)?;
+        {
+            let mut insert_blob =
+                tx.prepare("INSERT OR IGNORE INTO blobs (path, size) VALUES (?1, ?2)")?;
+            let mut select_blob =
+                tx.prepare("SELECT id FROM blobs WHERE path = ?1")?;
+            let mut insert_chunk = tx.prepare(
+                "INSERT INTO chunks (chunk_id, blob_id, offset, last_used) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(chunk_id) DO UPDATE SET blob_id = excluded.blob_id, offset = excluded.offset, last_used = excluded.last_used",
+            )?;
+
+            let ts = now();
+            let mut blob_ids: HashMap<&str, i64> = HashMap::new();
+            for (chunk_id, path, offset) in entries {
+                let blob_id = if let Some(id) = blob_ids.get(path.as_str()) {
+                    *id
+                } else {
+                    let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+                    insert_blob.execute(params![path, size as i64])?;
+                    let id: i64 = select_blob.query_row(params![path], |row| row.get(0))?;
+                    blob_ids.insert(path.as_str(), id);
+                    id
+                };
+                insert_chunk.execute(params![chunk_id, blob_id, *offset as i64, ts])?;
+            }
+        }
+        tx.commit().map_err(CasError::Db)?;
+        Ok(())
+    }
+
+    /// Update a chunk's `last_used` timestamp, marking it as still live.
+    pub fn touch_chunk(&self, chunk_id: &str) -> Result<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "UPDATE chunks SET last_used = ?1 WHERE chunk_id = ?2",
+            params![now(), chunk_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_chunk_info(&self, chunk_id: &str) -> Result<Option<(String, u64)>> {
+        let conn = self.pool.get()?;
+        let result = conn
+            .query_row(
+                "SELECT blobs.path, chunks.offset FROM chunks
+                 JOIN blobs ON blobs.id = chunks.blob_id
+                 WHERE chunks.chunk_id = ?1",
+                params![chunk_id],
+                |row| {
+                    let path: String = row.get(0)?;
+                    let offset: i64 = row.get(1)?;
+                    Ok((path, offset as u64))
+                },
+            )
+            .optional()?;
+        Ok(result)
+    }
+
+    pub fn get_all_blobs(&self) -> Result<Vec<(i64, String)>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare("SELECT id, path FROM blobs")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Remove a single chunk record, e.g. because a verify-on-dedup check
+    /// found its recorded `(path, offset)` no longer matches the digest.
+    pub fn delete_chunk(&self, chunk_id: &str) -> Result<()> {
+        let conn = self.pool.get()?;
+        conn.execute("DELETE FROM chunks WHERE chunk_id = ?1", params![chunk_id])?;
+        Ok(())
+    }
+
+    pub fn delete_blobs(&self, paths: &[String]) -> Result<()> {
+        let conn = self.pool.get()?;
+        for path in paths {
+            conn.execute(
+                "DELETE FROM chunks WHERE blob_id IN (SELECT id FROM blobs WHERE path = ?1)",
+                params![path],
+            )?;
+            conn.execute("DELETE FROM blobs WHERE path = ?1", params![path])?;
+        }
+        Ok(())
+    }
+
+    /// Refresh the `last_used` timestamp of every chunk belonging to a live blob.
+    ///
+    /// This is the "mark" phase of mark-and-sweep GC: callers walk the set of
+    /// blobs known to still be referenced by a live mount and touch their
+    /// chunks so the following sweep doesn't reclaim them.
+    pub fn mark_blob_live(&self, path: &str) -> Result<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "UPDATE chunks SET last_used = ?1 WHERE blob_id IN (SELECT id FROM blobs WHERE path = ?2)",
+            params![now(), path],
+        )?;
+        Ok(())
+    }
+
+    /// Sweep phase: delete chunk rows whose `last_used` predates `cutoff`
+    /// (a Unix timestamp), then prune blobs left with no chunks.
+    ///
+    /// `gc_lock` is held for the duration of the sweep. A chunk inserted by a
+    /// concurrent `add_chunk` after a cutoff in the past is never collected,
+    /// since its `last_used` is `now() >= cutoff`; a cutoff in the future
+    /// collects such chunks too.
+    pub fn sweep(&self, cutoff: i64) -> Result<GcStats> {
+        let _guard = self.gc_lock.lock().unwrap();
+        let conn = self.pool.get()?;
+        let reclaimed_chunks = conn.execute(
+            "DELETE FROM chunks WHERE last_used < ?1",
+            params![cutoff],
+        )? as u64;
+        let reclaimed_bytes: i64 = conn.query_row(
+            "SELECT COALESCE(SUM(size), 0) FROM blobs WHERE id NOT IN (SELECT DISTINCT blob_id FROM chunks)",
+            [],
+            |row| row.get(0),
+        )?;
+        let reclaimed_blobs = conn.execute(
+            "DELETE FROM blobs WHERE id NOT IN (SELECT DISTINCT blob_id FROM chunks)",
+            [],
+        )? as u64;
+        Ok(GcStats {
+            reclaimed_chunks,
+            reclaimed_blobs,
+            reclaimed_bytes: reclaimed_bytes as u64,
+        })
+    }
+}
+
+impl CasStore for CasDb {
+    fn get_chunk_info(&self, chunk_id: &str) -> Result<Option<(String, u64)>> {
+        CasDb::get_chunk_info(self, chunk_id)
+    }
+
+    fn add_blob(&self, path: &str) -> Result<i64> {
+        CasDb::add_blob(self, path)
+    }
+
+    fn add_chunk(&self, chunk_id: &str, offset: u64, path: &str) -> Result<()> {
+        CasDb::add_chunk(self, chunk_id, offset, path)
+    }
+
+    fn add_chunks_batch(&self, entries: &[(String, String, u64)]) -> Result<()> {
+        CasDb::add_chunks_batch(self, entries)
+    }
+
+    fn touch_chunk(&self, chunk_id: &str) -> Result<()> {
+        CasDb::touch_chunk(self, chunk_id)
+    }
+
+    fn delete_chunk(&self, chunk_id: &str) -> Result<()> {
+        CasDb::delete_chunk(self, chunk_id)
+    }
+
+    fn delete_blobs(&self, paths: &[String]) -> Result<()> {
+        CasDb::delete_blobs(self, paths)
+    }
+
+    fn get_all_blobs(&self) -> Result<Vec<(i64, String)>> {
+        CasDb::get_all_blobs(self)
+    }
+
+    fn mark_blob_live(&self, path: &str) -> Result<()> {
+        CasDb::mark_blob_live(self, path)
+    }
+
+    fn sweep(&self, cutoff: i64) -> Result<GcStats> {
+        CasDb::sweep(self, cutoff)
+    }
+
+    fn total_bytes(&self) -> Result<u64> {
+        CasDb::total_bytes(self)
+    }
+
+    fn evict_lru_blob(&self) -> Result<Option<(String, u64)>> {
+        CasDb::evict_lru_blob(self)
+    }
+}
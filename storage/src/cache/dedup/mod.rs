@@ -7,17 +7,31 @@ use std::collections::HashMap;
 use std::fmt::{self, Display, Formatter};
 use std::fs::{File, OpenOptions};
 use std::io::Error;
-use std::path::Path;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rayon::prelude::*;
 
 use nydus_utils::digest::RafsDigest;
 
-use crate::cache::dedup::db::CasDb;
+use crate::cache::dedup::db::{CasDb, GcStats};
 use crate::device::{BlobChunkInfo, BlobInfo};
 use crate::utils::copy_file_range;
 
+pub use crate::cache::dedup::db::CasStore;
+
 mod db;
 
+/// Default grace window applied before a chunk's last-access timestamp makes
+/// it eligible for collection, so a chunk inserted by a concurrent
+/// `record_chunk` is never swept out before it can be touched by a mount.
+const DEFAULT_GC_GRACE_SECS: i64 = 3600;
+
+/// Default on-disk budget for the dedup cache, so it doesn't grow unbounded.
+const DEFAULT_MAX_BYTES: u64 = 1 << 30; // 1 GiB
+
 lazy_static::lazy_static!(
     static ref CAS_MGR: Mutex<Option<Arc<CasMgr>>> = Mutex::new(None);
 );
@@ -64,20 +78,105 @@ impl From<Error> for CasError {
 type Result<T> = std::result::Result<T, CasError>;
 
 pub struct CasMgr {
-    db: CasDb,
+    db: Box<dyn CasStore>,
     fds: RwLock<HashMap<String, Arc<File>>>,
+    gc_grace_secs: i64,
+    max_bytes: u64,
+    verify: bool,
+    reflink: bool,
 }
 
 impl CasMgr {
     pub fn new(db_path: impl AsRef<Path>) -> Result<Self> {
         let db = CasDb::from_file(db_path.as_ref())?;
+        Self::with_store(Box::new(db))
+    }
 
+    /// Create a manager backed by an arbitrary [`CasStore`] implementation
+    /// instead of the default Sqlite-backed [`CasDb`], e.g. a remote/shared
+    /// index or an in-memory store.
+    pub fn with_store(db: Box<dyn CasStore>) -> Result<Self> {
         Ok(CasMgr {
             db,
             fds: RwLock::new(HashMap::new()),
+            gc_grace_secs: DEFAULT_GC_GRACE_SECS,
+            max_bytes: DEFAULT_MAX_BYTES,
+            verify: false,
+            reflink: false,
         })
     }
 
+    /// Create a manager that attempts a copy-on-write reflink clone on a
+    /// dedup hit instead of a plain byte copy, so the cache file and the
+    /// source blob physically share extents and the CAS becomes a genuine
+    /// storage-deduplication layer instead of just avoiding network fetches.
+    /// Requires a filesystem that supports `FICLONERANGE` (e.g. btrfs, XFS);
+    /// falls back to a byte copy transparently when cloning isn't possible.
+    pub fn new_with_reflink(db_path: impl AsRef<Path>, reflink: bool) -> Result<Self> {
+        let mut mgr = Self::new(db_path)?;
+        mgr.reflink = reflink;
+        Ok(mgr)
+    }
+
+    /// Create a manager that re-validates a dedup hit's source bytes against
+    /// the requested chunk digest before serving it, guarding against digest
+    /// collisions or the source file having been overwritten since it was
+    /// recorded. Safe to use when the blob files may be shared or untrusted.
+    pub fn new_with_verify(db_path: impl AsRef<Path>, verify: bool) -> Result<Self> {
+        let mut mgr = Self::new(db_path)?;
+        mgr.verify = verify;
+        Ok(mgr)
+    }
+
+    /// Create a manager with a custom GC grace window, in seconds, instead
+    /// of the default. Chunks more recently used than the window are never
+    /// collected, regardless of how old the GC cutoff is.
+    pub fn new_with_gc_grace(db_path: impl AsRef<Path>, gc_grace_secs: i64) -> Result<Self> {
+        let mut mgr = Self::new(db_path)?;
+        mgr.gc_grace_secs = gc_grace_secs;
+        Ok(mgr)
+    }
+
+    /// Create a manager with a maximum on-disk size, in bytes, instead of
+    /// the default 1 GiB. Once the recorded blobs would exceed this budget,
+    /// `record_chunk`/`record_chunk_raw` evict least-recently-used blobs
+    /// until back under budget.
+    pub fn new_with_limit(db_path: impl AsRef<Path>, max_bytes: u64) -> Result<Self> {
+        let mut mgr = Self::new(db_path)?;
+        mgr.max_bytes = max_bytes;
+        Ok(mgr)
+    }
+
+    /// Evict least-recently-used blobs until the total recorded size is back
+    /// under `max_bytes`, removing their rows from the DB, their entry from
+    /// the `fds` cache, and unlinking the backing file.
+    fn enforce_disk_budget(&self) {
+        loop {
+            match self.db.total_bytes() {
+                Ok(total) if total <= self.max_bytes => return,
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("failed to query dedup cache size: {}", e);
+                    return;
+                }
+            }
+
+            match self.db.evict_lru_blob() {
+                Ok(Some((path, _size))) => {
+                    self.fds.write().unwrap().remove(&path);
+                    if let Err(e) = std::fs::remove_file(&path) {
+                        warn!("failed to unlink evicted dedup blob {}: {}", path, e);
+                    }
+                }
+                Ok(None) => return,
+                Err(e) => {
+                    warn!("failed to evict LRU dedup blob: {}", e);
+                    return;
+                }
+            }
+        }
+    }
+
     pub fn set_singleton(mgr: CasMgr) {
         *CAS_MGR.lock().unwrap() = Some(Arc::new(mgr));
     }
@@ -112,7 +211,7 @@ impl CasMgr {
                         Err(e) => warn!("failed to open dedup source file {}, {}", path, e),
                         Ok(f) => {
                             let mut guard = self.fds.write().unwrap();
-                            match guard.entry(path) {
+                            match guard.entry(path.clone()) {
                                 Entry::Vacant(e) => {
                                     let f = Arc::new(f);
                                     e.insert(f.clone());
@@ -140,14 +239,32 @@ impl CasMgr {
             }
 
             if let Some(f) = d_file {
+                if self.verify && !self.verify_source(&f, offset, blob, chunk) {
+                    warn!(
+                        "dedup source {}@{} no longer matches digest of chunk {}, evicting stale record",
+                        path, offset, key
+                    );
+                    if let Err(e) = self.db.delete_chunk(&key) {
+                        warn!("failed to evict stale dedup chunk {}: {}", key, e);
+                    }
+                    self.fds.write().unwrap().remove(&path);
+                    return false;
+                }
+
                 match copy_file_range(
                     f,
                     offset,
                     cache_file,
                     chunk.uncompressed_offset(),
                     chunk.uncompressed_size() as usize,
+                    self.reflink,
                 ) {
                     Ok(_) => {
+                        // Touch the chunk so a concurrent GC sweep knows it's
+                        // still being referenced by a live mount.
+                        if let Err(e) = self.db.touch_chunk(&key) {
+                            warn!("failed to touch dedup chunk {}: {}", key, e);
+                        }
                         return true;
                     }
                     Err(e) => warn!("{e}"),
@@ -176,11 +293,82 @@ impl CasMgr {
     }
 
     pub fn record_chunk_raw(&self, chunk_id: &str, path: &str, offset: u64) -> Result<()> {
-        self.db.add_blob(path)?;
-        self.db.add_chunk(chunk_id, offset, path)?;
+        self.record_chunks(&[(chunk_id.to_string(), path.to_string(), offset)])
+    }
+
+    /// Record many chunks in a single database transaction, which is orders
+    /// of magnitude faster than calling `record_chunk`/`record_chunk_raw`
+    /// once per chunk when registering an image with millions of chunks.
+    pub fn record_chunks(&self, entries: &[(String, String, u64)]) -> Result<()> {
+        self.db.add_chunks_batch(entries)?;
+        self.enforce_disk_budget();
         Ok(())
     }
 
+    /// Bootstrap the dedup index from an existing blob-cache directory.
+    ///
+    /// Walks `dir` in parallel (one rayon task per file) and uses
+    /// `chunk_loader` to map each regular file to the `(chunk_id, offset)`
+    /// pairs it contains, then batch-records everything found. `storage`
+    /// sits below `rafs` in the crate dependency graph, so the RAFS-aware
+    /// mapping from a blob file to its chunks can't live here and must be
+    /// supplied by the caller (e.g. reading the bootstrap's chunk table).
+    ///
+    /// This lets a freshly started node seed its dedup index from a
+    /// populated cache directory instead of only ever learning chunks
+    /// lazily as they're fetched.
+    pub fn import_blob_dir(
+        &self,
+        dir: impl AsRef<Path>,
+        chunk_loader: impl Fn(&Path) -> std::io::Result<Vec<(String, u64)>> + Sync,
+    ) -> Result<()> {
+        let files: Vec<PathBuf> = std::fs::read_dir(dir.as_ref())?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_file())
+            .collect();
+
+        let entries: Vec<(String, String, u64)> = files
+            .par_iter()
+            .flat_map(|path| {
+                let path_str = path.display().to_string();
+                match chunk_loader(path) {
+                    Ok(chunks) => chunks
+                        .into_iter()
+                        .map(|(chunk_id, offset)| (chunk_id, path_str.clone(), offset))
+                        .collect(),
+                    Err(e) => {
+                        warn!("failed to load chunks from {}: {}", path_str, e);
+                        Vec::new()
+                    }
+                }
+            })
+            .collect();
+
+        if !entries.is_empty() {
+            self.record_chunks(&entries)?;
+        }
+        Ok(())
+    }
+
+    /// Re-read the source region and recompute its digest, comparing it
+    /// against the requested chunk's digest. Any I/O error is treated as a
+    /// verification failure, since we can no longer trust the dedup source.
+    fn verify_source(
+        &self,
+        source: &File,
+        offset: u64,
+        blob: &BlobInfo,
+        chunk: &dyn BlobChunkInfo,
+    ) -> bool {
+        let mut buf = vec![0u8; chunk.uncompressed_size() as usize];
+        if let Err(e) = source.read_exact_at(&mut buf, offset) {
+            warn!("failed to re-read dedup source for verification: {}", e);
+            return false;
+        }
+        RafsDigest::from_buf(&buf, blob.digester()) == *chunk.chunk_id()
+    }
+
     fn chunk_key(blob: &BlobInfo, chunk: &dyn BlobChunkInfo) -> String {
         let id = chunk.chunk_id();
         if *id == RafsDigest::default() {
@@ -190,8 +378,24 @@ impl CasMgr {
         }
     }
 
-    /// Check if blobs in the database still exist on the filesystem and perform garbage collection.
-    pub fn gc(&self) -> Result<()> {
+    /// Two-phase mark-and-sweep garbage collection over the chunk database.
+    ///
+    /// Phase 1 (mark): blobs whose backing file has vanished are dropped
+    /// outright; `live_blobs`, if given, is a list of blobs known to still be
+    /// referenced by a live mount, and their chunks are "touched" so phase 2
+    /// won't reclaim them even if they haven't been read in a while.
+    ///
+    /// Phase 2 (sweep): chunk rows whose `last_used` timestamp is older than
+    /// `now - gc_grace_secs` are deleted, and any blob left with zero chunks
+    /// is pruned. The grace window guards against racing a concurrent
+    /// `record_chunk` that inserted a chunk but hasn't been touched by a
+    /// mount yet.
+    ///
+    /// Returns the number of reclaimed blob rows from both phases and their
+    /// total recorded size, plus the number of chunk rows deleted by the
+    /// sweep, so callers can log GC stats. Chunk rows dropped together with a
+    /// vanished blob in phase 1 are not included in `reclaimed_chunks`.
+    pub fn gc(&self, live_blobs: &[impl AsRef<str>]) -> Result<GcStats> {
         let all_blobs = self.db.get_all_blobs()?;
         let mut blobs_not_exist = Vec::new();
         for (_, file_path) in all_blobs {
@@ -201,29 +405,53 @@ impl CasMgr {
         }
 
         // If there are any non-existent blobs, delete them from the database.
+        let mut vanished_bytes = 0;
         if !blobs_not_exist.is_empty() {
+            let bytes_before = self.db.total_bytes()?;
             self.db.delete_blobs(&blobs_not_exist).map_err(|e| {
                 warn!("failed to delete blobs: {}", e);
                 e
             })?;
+            vanished_bytes = bytes_before.saturating_sub(self.db.total_bytes()?);
         }
 
-        let mut guard = self.fds.write().unwrap();
-        for path in blobs_not_exist {
-            // Remove the non-existent blob paths from the cache.
-            guard.remove(&path);
+        {
+            let mut guard = self.fds.write().unwrap();
+            for path in &blobs_not_exist {
+                // Remove the non-existent blob paths from the cache.
+                guard.remove(path);
+            }
         }
 
-        Ok(())
+        // Mark phase: refresh chunks belonging to blobs that are still live.
+        for blob in live_blobs {
+            if let Err(e) = self.db.mark_blob_live(blob.as_ref()) {
+                warn!("failed to mark blob {} as live: {}", blob.as_ref(), e);
+            }
+        }
+
+        let cutoff = now_secs() - self.gc_grace_secs;
+        let mut stats = self.db.sweep(cutoff)?;
+        stats.reclaimed_blobs += blobs_not_exist.len() as u64;
+        stats.reclaimed_bytes += vanished_bytes;
+        Ok(stats)
     }
 }
 
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::device::BlobFeatures;
     use crate::test::MockChunkInfo;
     use crate::RAFS_DEFAULT_CHUNK_SIZE;
+    use std::fs;
     use std::io::{Read, Seek, SeekFrom, Write};
     use vmm_sys_util::tempfile::TempFile;
 
@@ -467,12 +695,48 @@ mod tests {
         assert_eq!(all_blobs_before_gc.len(), 1);
 
         drop(tmpfile);
-        mgr.gc().unwrap();
+        let stats = mgr.gc(&[] as &[&str]).unwrap();
+        assert_eq!(stats.reclaimed_blobs, 1);
 
         let all_blobs_after_gc = mgr.db.get_all_blobs().unwrap();
         assert_eq!(all_blobs_after_gc.len(), 0);
     }
 
+    #[test]
+    fn test_cas_gc_reports_reclaimed_bytes() {
+        let dbfile = TempFile::new().unwrap();
+        let mgr = CasMgr::new(dbfile.as_path()).unwrap();
+
+        let tmpfile = TempFile::new().unwrap();
+        fs::write(tmpfile.as_path(), [0u8; 4096]).unwrap();
+        let blob_path = tmpfile
+            .as_path()
+            .canonicalize()
+            .unwrap()
+            .display()
+            .to_string();
+        let blob = BlobInfo::new(
+            1,
+            blob_path.clone(),
+            8192,
+            8192,
+            RAFS_DEFAULT_CHUNK_SIZE as u32,
+            1,
+            BlobFeatures::empty(),
+        );
+        let mut chunk = MockChunkInfo::new();
+        chunk.block_id = RafsDigest { data: [11u8; 32] };
+        chunk.uncompress_offset = 0;
+        chunk.uncompress_size = 8192;
+        let chunk = Arc::new(chunk) as Arc<dyn BlobChunkInfo>;
+        mgr.record_chunk(&blob, chunk.as_ref(), &blob_path).unwrap();
+
+        drop(tmpfile);
+        let stats = mgr.gc(&[] as &[&str]).unwrap();
+        assert_eq!(stats.reclaimed_blobs, 1);
+        assert_eq!(stats.reclaimed_bytes, 4096);
+    }
+
     #[test]
     fn test_cas_gc_with_existing_files() {
         let dbfile = TempFile::new().unwrap();
@@ -503,10 +767,300 @@ mod tests {
         mgr.record_chunk(&blob, chunk.as_ref(), &blob_path).unwrap();
 
         // GC with file still existing
-        mgr.gc().unwrap();
+        mgr.gc(&[] as &[&str]).unwrap();
 
         let all_blobs_after_gc = mgr.db.get_all_blobs().unwrap();
         // File still exists, so it should remain
         assert_eq!(all_blobs_after_gc.len(), 1);
     }
+
+    #[test]
+    fn test_cas_gc_respects_grace_window() {
+        let dbfile = TempFile::new().unwrap();
+        let mgr = CasMgr::new_with_gc_grace(dbfile.as_path(), -1).unwrap();
+
+        let tmpfile = TempFile::new().unwrap();
+        let blob_path = tmpfile
+            .as_path()
+            .canonicalize()
+            .unwrap()
+            .display()
+            .to_string();
+
+        let blob = BlobInfo::new(
+            1,
+            blob_path.clone(),
+            8192,
+            8192,
+            RAFS_DEFAULT_CHUNK_SIZE as u32,
+            1,
+            BlobFeatures::empty(),
+        );
+        let mut chunk = MockChunkInfo::new();
+        chunk.block_id = RafsDigest { data: [7u8; 32] };
+        chunk.uncompress_offset = 0;
+        chunk.uncompress_size = 8192;
+        let chunk = Arc::new(chunk) as Arc<dyn BlobChunkInfo>;
+        mgr.record_chunk(&blob, chunk.as_ref(), &blob_path).unwrap();
+
+        // A negative grace window means the cutoff is in the future, so the
+        // freshly recorded chunk is immediately eligible for collection.
+        let stats = mgr.gc(&[] as &[&str]).unwrap();
+        assert_eq!(stats.reclaimed_chunks, 1);
+        assert_eq!(stats.reclaimed_blobs, 1);
+    }
+
+    #[test]
+    fn test_cas_disk_budget_evicts_lru_blob() {
+        let dbfile = TempFile::new().unwrap();
+        // Budget fits only one 8192-byte blob.
+        let mgr = CasMgr::new_with_limit(dbfile.as_path(), 8192).unwrap();
+
+        let tmpfile1 = TempFile::new().unwrap();
+        tmpfile1.as_file().set_len(8192).unwrap();
+        let path1 = tmpfile1.as_path().canonicalize().unwrap().display().to_string();
+        let blob1 = BlobInfo::new(
+            1,
+            path1.clone(),
+            8192,
+            8192,
+            RAFS_DEFAULT_CHUNK_SIZE as u32,
+            1,
+            BlobFeatures::empty(),
+        );
+        let mut chunk1 = MockChunkInfo::new();
+        chunk1.block_id = RafsDigest { data: [1u8; 32] };
+        chunk1.uncompress_offset = 0;
+        chunk1.uncompress_size = 8192;
+        let chunk1 = Arc::new(chunk1) as Arc<dyn BlobChunkInfo>;
+        mgr.record_chunk(&blob1, chunk1.as_ref(), &path1).unwrap();
+
+        let tmpfile2 = TempFile::new().unwrap();
+        tmpfile2.as_file().set_len(8192).unwrap();
+        let path2 = tmpfile2.as_path().canonicalize().unwrap().display().to_string();
+        let blob2 = BlobInfo::new(
+            2,
+            path2.clone(),
+            8192,
+            8192,
+            RAFS_DEFAULT_CHUNK_SIZE as u32,
+            1,
+            BlobFeatures::empty(),
+        );
+        let mut chunk2 = MockChunkInfo::new();
+        chunk2.block_id = RafsDigest { data: [2u8; 32] };
+        chunk2.uncompress_offset = 0;
+        chunk2.uncompress_size = 8192;
+        let chunk2 = Arc::new(chunk2) as Arc<dyn BlobChunkInfo>;
+        mgr.record_chunk(&blob2, chunk2.as_ref(), &path2).unwrap();
+
+        // Recording blob2 should have evicted the older blob1 to stay under budget.
+        let remaining = mgr.db.get_all_blobs().unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].1, path2);
+        assert!(mgr.db.total_bytes().unwrap() <= 8192);
+    }
+
+    #[test]
+    fn test_record_chunks_batch() {
+        let dbfile = TempFile::new().unwrap();
+        let mgr = CasMgr::new(dbfile.as_path()).unwrap();
+
+        let tmpfile1 = TempFile::new().unwrap();
+        let path1 = tmpfile1.as_path().display().to_string();
+        let tmpfile2 = TempFile::new().unwrap();
+        let path2 = tmpfile2.as_path().display().to_string();
+
+        let entries = vec![
+            ("digest:aaa".to_string(), path1.clone(), 0u64),
+            ("digest:bbb".to_string(), path1.clone(), 4096u64),
+            ("digest:ccc".to_string(), path2.clone(), 0u64),
+        ];
+        mgr.record_chunks(&entries).unwrap();
+
+        let blobs = mgr.db.get_all_blobs().unwrap();
+        assert_eq!(blobs.len(), 2);
+        assert!(mgr.db.get_chunk_info("digest:aaa").unwrap().is_some());
+        assert!(mgr.db.get_chunk_info("digest:bbb").unwrap().is_some());
+        assert!(mgr.db.get_chunk_info("digest:ccc").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_dedup_verify_rejects_corrupted_source() {
+        let dbfile = TempFile::new().unwrap();
+        let mgr = CasMgr::new_with_verify(dbfile.as_path(), true).unwrap();
+
+        let tmpfile = TempFile::new().unwrap();
+        let src_path = tmpfile.as_path().display().to_string();
+        let blob = BlobInfo::new(
+            1,
+            src_path.clone(),
+            8192,
+            8192,
+            RAFS_DEFAULT_CHUNK_SIZE as u32,
+            1,
+            BlobFeatures::empty(),
+        );
+
+        let buf = vec![0x9u8; 8192];
+        let digest = RafsDigest::from_buf(&buf, blob.digester());
+        let mut chunk = MockChunkInfo::new();
+        chunk.block_id = digest;
+        chunk.uncompress_offset = 0;
+        chunk.uncompress_size = 8192;
+        let chunk = Arc::new(chunk) as Arc<dyn BlobChunkInfo>;
+
+        let mut src_file = tmpfile.as_file().try_clone().unwrap();
+        src_file.write_all(&buf).unwrap();
+        mgr.record_chunk(&blob, chunk.as_ref(), &src_path).unwrap();
+
+        // Overwrite the source after recording, so the digest no longer matches.
+        src_file.seek(SeekFrom::Start(0)).unwrap();
+        src_file.write_all(&vec![0xAu8; 8192]).unwrap();
+
+        let cache_file = TempFile::new().unwrap().into_file();
+        assert!(!mgr.dedup_chunk(&blob, chunk.as_ref(), &cache_file));
+
+        // The stale record must have been evicted.
+        let key = CasMgr::chunk_key(&blob, chunk.as_ref());
+        assert!(mgr.db.get_chunk_info(&key).unwrap().is_none());
+    }
+
+    /// A trivial in-memory [`CasStore`] used to prove `CasMgr` works against
+    /// any backend, not just the Sqlite-backed `CasDb`.
+    #[derive(Default)]
+    struct MemStore {
+        chunks: Mutex<HashMap<String, (String, u64)>>,
+    }
+
+    impl crate::cache::dedup::db::CasStore for MemStore {
+        fn get_chunk_info(
+            &self,
+            chunk_id: &str,
+        ) -> std::result::Result<Option<(String, u64)>, CasError> {
+            Ok(self.chunks.lock().unwrap().get(chunk_id).cloned())
+        }
+
+        fn add_blob(&self, _path: &str) -> std::result::Result<i64, CasError> {
+            Ok(0)
+        }
+
+        fn add_chunk(
+            &self,
+            chunk_id: &str,
+            offset: u64,
+            path: &str,
+        ) -> std::result::Result<(), CasError> {
+            self.chunks
+                .lock()
+                .unwrap()
+                .insert(chunk_id.to_string(), (path.to_string(), offset));
+            Ok(())
+        }
+
+        fn add_chunks_batch(
+            &self,
+            entries: &[(String, String, u64)],
+        ) -> std::result::Result<(), CasError> {
+            for (chunk_id, path, offset) in entries {
+                self.add_chunk(chunk_id, *offset, path)?;
+            }
+            Ok(())
+        }
+
+        fn touch_chunk(&self, _chunk_id: &str) -> std::result::Result<(), CasError> {
+            Ok(())
+        }
+
+        fn delete_chunk(&self, chunk_id: &str) -> std::result::Result<(), CasError> {
+            self.chunks.lock().unwrap().remove(chunk_id);
+            Ok(())
+        }
+
+        fn delete_blobs(&self, _paths: &[String]) -> std::result::Result<(), CasError> {
+            Ok(())
+        }
+
+        fn get_all_blobs(&self) -> std::result::Result<Vec<(i64, String)>, CasError> {
+            Ok(Vec::new())
+        }
+
+        fn mark_blob_live(&self, _path: &str) -> std::result::Result<(), CasError> {
+            Ok(())
+        }
+
+        fn sweep(&self, _cutoff: i64) -> std::result::Result<db::GcStats, CasError> {
+            Ok(db::GcStats::default())
+        }
+
+        fn total_bytes(&self) -> std::result::Result<u64, CasError> {
+            Ok(0)
+        }
+
+        fn evict_lru_blob(&self) -> std::result::Result<Option<(String, u64)>, CasError> {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn test_cas_mgr_with_pluggable_store() {
+        let mgr = CasMgr::with_store(Box::new(MemStore::default())).unwrap();
+
+        let blob = BlobInfo::new(
+            1,
+            "test".to_string(),
+            8192,
+            8192,
+            RAFS_DEFAULT_CHUNK_SIZE as u32,
+            1,
+            BlobFeatures::empty(),
+        );
+        let mut chunk = MockChunkInfo::new();
+        chunk.block_id = RafsDigest { data: [9u8; 32] };
+        let chunk = Arc::new(chunk) as Arc<dyn BlobChunkInfo>;
+
+        let tmpfile = TempFile::new().unwrap();
+        let blob_path = tmpfile
+            .as_path()
+            .canonicalize()
+            .unwrap()
+            .display()
+            .to_string();
+        mgr.record_chunk(&blob, chunk.as_ref(), &blob_path).unwrap();
+        let key = CasMgr::chunk_key(&blob, chunk.as_ref());
+        assert_eq!(
+            mgr.db.get_chunk_info(&key).unwrap(),
+            Some((blob_path, 0))
+        );
+    }
+
+    #[test]
+    fn test_import_blob_dir() {
+        let dbfile = TempFile::new().unwrap();
+        let mgr = CasMgr::new(dbfile.as_path()).unwrap();
+
+        let mut dir_path = std::env::temp_dir();
+        dir_path.push(format!("nydus-import-blob-dir-test-{}", std::process::id()));
+        fs::create_dir_all(&dir_path).unwrap();
+        let blob_file = TempFile::new_with_prefix(dir_path.join("blob")).unwrap();
+        blob_file.as_file().set_len(8192).unwrap();
+        let blob_path = blob_file.as_path().display().to_string();
+
+        mgr.import_blob_dir(&dir_path, |path| {
+            if path.display().to_string() == blob_path {
+                Ok(vec![("digest:imported".to_string(), 0)])
+            } else {
+                Ok(vec![])
+            }
+        })
+        .unwrap();
+
+        assert!(mgr
+            .db
+            .get_chunk_info("digest:imported")
+            .unwrap()
+            .is_some());
+
+        fs::remove_dir_all(&dir_path).unwrap();
+    }
 }
@@ -0,0 +1,139 @@
+// Copyright (C) 2022-2023 Alibaba Cloud. All rights reserved.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::fs::File;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::sync::Arc;
+
+/// Mirrors the kernel's `struct file_clone_range` used by `FICLONERANGE`.
+#[repr(C)]
+struct FileCloneRange {
+    src_fd: i64,
+    src_offset: u64,
+    src_length: u64,
+    dest_offset: u64,
+}
+
+// _IOW(0x94, 13, struct file_clone_range)
+const FICLONERANGE: libc::c_ulong = 0x4020_940d;
+
+/// Attempt a copy-on-write clone of `len` bytes from `src` to `dst` via the
+/// `FICLONERANGE` ioctl, so the two files physically share extents instead
+/// of duplicating the underlying storage. Only btrfs/XFS-like filesystems
+/// support this, and only when source and destination live on the same
+/// filesystem with compatible block alignment.
+fn try_reflink(src: &File, src_offset: u64, dst: &File, dst_offset: u64, len: u64) -> io::Result<()> {
+    let range = FileCloneRange {
+        src_fd: src.as_raw_fd() as i64,
+        src_offset,
+        src_length: len,
+        dest_offset: dst_offset,
+    };
+    let ret = unsafe { libc::ioctl(dst.as_raw_fd(), FICLONERANGE as _, &range) };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Copy `size` bytes from `src` at `src_offset` into `dst` at `dst_offset`.
+///
+/// When `reflink` is set, first attempt a zero-copy clone via `FICLONERANGE`
+/// so the dedup cache file shares extents with the source blob instead of
+/// consuming extra disk space; this falls back to a regular byte copy via
+/// the `copy_file_range(2)` syscall whenever cloning isn't possible (cross
+/// filesystem, unsupported filesystem, misaligned offsets, ...).
+pub fn copy_file_range(
+    src: Arc<File>,
+    src_offset: u64,
+    dst: &File,
+    dst_offset: u64,
+    size: usize,
+    reflink: bool,
+) -> io::Result<usize> {
+    if reflink && try_reflink(&src, src_offset, dst, dst_offset, size as u64).is_ok() {
+        return Ok(size);
+    }
+
+    let mut off_in = src_offset as i64;
+    let mut off_out = dst_offset as i64;
+    let mut remaining = size;
+    while remaining > 0 {
+        let ret = unsafe {
+            libc::copy_file_range(
+                src.as_raw_fd(),
+                &mut off_in,
+                dst.as_raw_fd(),
+                &mut off_out,
+                remaining,
+                0,
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if ret == 0 {
+            break;
+        }
+        remaining -= ret as usize;
+    }
+
+    Ok(size - remaining)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Seek, SeekFrom, Write};
+    use vmm_sys_util::tempfile::TempFile;
+
+    #[test]
+    fn test_copy_file_range_byte_copy() {
+        let src_tmp = TempFile::new().unwrap();
+        {
+            let mut src_file = src_tmp.as_file().try_clone().unwrap();
+            src_file.write_all(&vec![0x7u8; 4096]).unwrap();
+        }
+
+        let dst_tmp = TempFile::new().unwrap();
+        let dst_file = dst_tmp.as_file().try_clone().unwrap();
+        let src = Arc::new(src_tmp.into_file());
+
+        let n = copy_file_range(src, 0, &dst_file, 0, 4096, false).unwrap();
+        assert_eq!(n, 4096);
+
+        let mut dst_file = dst_file;
+        dst_file.seek(SeekFrom::Start(0)).unwrap();
+        let mut out = vec![0u8; 4096];
+        dst_file.read_exact(&mut out).unwrap();
+        assert_eq!(out, vec![0x7u8; 4096]);
+    }
+
+    #[test]
+    fn test_copy_file_range_reflink_mode_produces_correct_bytes() {
+        // Whether the underlying filesystem clones the range or the call
+        // falls back to a byte copy, it must succeed and produce identical
+        // bytes.
+        let src_tmp = TempFile::new().unwrap();
+        {
+            let mut src_file = src_tmp.as_file().try_clone().unwrap();
+            src_file.write_all(&vec![0x3u8; 4096]).unwrap();
+        }
+
+        let dst_tmp = TempFile::new().unwrap();
+        let dst_file = dst_tmp.as_file().try_clone().unwrap();
+        let src = Arc::new(src_tmp.into_file());
+
+        let n = copy_file_range(src, 0, &dst_file, 0, 4096, true).unwrap();
+        assert_eq!(n, 4096);
+
+        let mut dst_file = dst_file;
+        dst_file.seek(SeekFrom::Start(0)).unwrap();
+        let mut out = vec![0u8; 4096];
+        dst_file.read_exact(&mut out).unwrap();
+        assert_eq!(out, vec![0x3u8; 4096]);
+    }
+}
@@ -12,6 +12,13 @@ use versionize::{VersionMap, Versionize};
 /// A list of versions.
 type Versions = Vec<HashMap<TypeId, u16>>;
 
+/// Magic bytes prepended to every buffer produced by [Snapshotter::save], identifying
+/// it as carrying an explicit version header rather than a bare snapshot payload.
+const HEADER_MAGIC: [u8; 4] = *b"NYSV";
+
+/// Size in bytes of the magic + target version header prepended by `save`.
+const HEADER_LEN: usize = HEADER_MAGIC.len() + std::mem::size_of::<u16>();
+
 /// A trait for snapshotting.
 /// This trait is used to save and restore a struct
 /// which implements `versionize::Versionize`.
@@ -40,21 +47,83 @@ pub trait Snapshotter: Versionize + Sized + Debug {
         Snapshot::new(vm, target_version)
     }
 
-    /// Saves the struct to a `Vec<u8>`.
+    /// Saves the struct to a `Vec<u8>`, prefixed with a small header recording the
+    /// target version it was encoded with, so a future build can tell which version
+    /// to restore at instead of always assuming its own latest.
     fn save(&self) -> Result<Vec<u8>> {
-        let mut buf = Vec::new();
+        let target_version = Self::new_version_map().latest_version();
+
+        let mut payload = Vec::new();
         let mut snapshot = Self::new_snapshot();
         snapshot
-            .save(&mut buf, self)
+            .save(&mut payload, self)
             .map_err(|e| IoError::other(format!("Failed to save snapshot: {:?}", e)))?;
 
+        let mut buf = Vec::with_capacity(HEADER_LEN + payload.len());
+        buf.extend_from_slice(&HEADER_MAGIC);
+        buf.extend_from_slice(&target_version.to_le_bytes());
+        buf.extend_from_slice(&payload);
         Ok(buf)
     }
 
-    /// Restores the struct from a `Vec<u8>`.
+    /// Reads the version header written by `save`, without decoding the payload.
+    fn peek_version(buf: &[u8]) -> Result<u16> {
+        if buf.len() < HEADER_LEN || buf[..HEADER_MAGIC.len()] != HEADER_MAGIC {
+            return Err(IoError::other(
+                "snapshot buffer is missing the expected version header",
+            ));
+        }
+        let mut version_bytes = [0u8; 2];
+        version_bytes.copy_from_slice(&buf[HEADER_MAGIC.len()..HEADER_LEN]);
+        Ok(u16::from_le_bytes(version_bytes))
+    }
+
+    /// Restores the struct from a `Vec<u8>` produced by `save`, reading the source
+    /// version from its header instead of unconditionally assuming the latest.
     fn restore(buf: &mut Vec<u8>) -> Result<Self> {
-        match Snapshot::load(&mut buf.as_slice(), buf.len(), Self::new_version_map()) {
-            Ok((o, _)) => Ok(o),
+        let version = Self::peek_version(buf)?;
+        Self::restore_at_version(buf, version)
+    }
+
+    /// Restores the struct from a `Vec<u8>`, asserting that it was encoded at
+    /// `version`. Useful when the caller already knows the source version out of
+    /// band (e.g. from an out-of-band manifest) and wants to fail loudly rather
+    /// than silently decode a buffer written by a different version.
+    ///
+    /// Note `version` does not itself select how the payload is decoded: the
+    /// underlying `dbs_snapshot::Snapshot` format embeds its own data-version
+    /// header in the payload and always decodes against that, falling back to
+    /// each field's `default_fn` for versions where it didn't yet exist. This
+    /// method additionally checks that the embedded version matches `version`,
+    /// so a caller-supplied expectation that doesn't hold is reported as an
+    /// error instead of being silently ignored.
+    fn restore_at_version(buf: &mut Vec<u8>, version: u16) -> Result<Self> {
+        if buf.len() < HEADER_LEN || buf[..HEADER_MAGIC.len()] != HEADER_MAGIC {
+            return Err(IoError::other(
+                "snapshot buffer is missing the expected version header",
+            ));
+        }
+
+        let version_map = Self::new_version_map();
+        if version == 0 || version > version_map.latest_version() {
+            return Err(IoError::other(format!(
+                "snapshot version {} is not supported by this build (latest known: {})",
+                version,
+                version_map.latest_version()
+            )));
+        }
+
+        let payload = &buf[HEADER_LEN..];
+        match Snapshot::load(&mut &payload[..], payload.len(), version_map) {
+            Ok((o, decoded_version)) => {
+                if decoded_version != version {
+                    return Err(IoError::other(format!(
+                        "snapshot payload was encoded at version {} but version {} was requested",
+                        decoded_version, version
+                    )));
+                }
+                Ok(o)
+            }
             Err(e) => Err(IoError::other(format!("Failed to load snapshot: {:?}", e))),
         }
     }
@@ -192,4 +261,55 @@ mod tests {
         let result = TestStruct::restore(&mut empty_buf);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_snapshotter_save_writes_version_header() {
+        let original = TestStruct {
+            value: 1,
+            name: "v1".to_string(),
+        };
+
+        let buf = original.save().unwrap();
+        assert_eq!(TestStruct::peek_version(&buf).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_snapshotter_restore_at_version() {
+        let original = TestStruct {
+            value: 7,
+            name: "pinned".to_string(),
+        };
+
+        let mut buf = original.save().unwrap();
+        let restored = TestStruct::restore_at_version(&mut buf, 1).unwrap();
+        assert_eq!(original, restored);
+    }
+
+    #[test]
+    fn test_snapshotter_restore_at_version_rejects_unknown_version() {
+        let original = TestStruct {
+            value: 7,
+            name: "pinned".to_string(),
+        };
+
+        let mut buf = original.save().unwrap();
+        let result = TestStruct::restore_at_version(&mut buf, 99);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_snapshotter_restore_rejects_missing_header() {
+        // A bare `versionize` payload with no magic/version header prepended must
+        // be rejected rather than silently misparsed as header bytes.
+        let original = TestStruct {
+            value: 1,
+            name: "bare".to_string(),
+        };
+        let mut snapshot = TestStruct::new_snapshot();
+        let mut bare_payload = Vec::new();
+        snapshot.save(&mut bare_payload, &original).unwrap();
+
+        let result = TestStruct::restore(&mut bare_payload);
+        assert!(result.is_err());
+    }
 }